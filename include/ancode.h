@@ -0,0 +1,27 @@
+/* C interface to the AnCode compiler front end. Build the shared library with
+ * `cargo rustc --lib --release --features capi --crate-type cdylib` */
+#ifndef ANCODE_H
+#define ANCODE_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct AncodeResult AncodeResult;
+
+/* Compiles `source`, using `file_name` in diagnostics. Returns NULL if either argument is NULL
+ * or the compiler hit an internal error. The result must be released with ancode_free. */
+AncodeResult *ancode_compile(const char *file_name, const char *source);
+
+/* Every rendered diagnostic, or an empty string if there were none. Source lines are shown with
+ * line number gutters, so no diagnostic contains a blank line and each is separated from the
+ * next by exactly one. Owned by the result. */
+const char *ancode_get_diagnostics(const AncodeResult *result);
+
+void ancode_free(AncodeResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,107 @@
+//C bindings to the compiler front end, declared in include/ancode.h
+use std::ffi::{c_char, CStr, CString};
+
+use crate::compile::compile;
+use crate::session::Options;
+
+pub struct AncodeResult {
+    diagnostics: CString,
+}
+
+/// # Safety
+/// `file_name` and `source` must be null or valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn ancode_compile(file_name: *const c_char, source: *const c_char) -> *mut AncodeResult {
+    if file_name.is_null() || source.is_null() {
+        return std::ptr::null_mut();
+    }
+    let file_name = CStr::from_ptr(file_name).to_string_lossy().into_owned();
+    let source = CStr::from_ptr(source).to_string_lossy().into_owned();
+
+    //unwinding into C is undefined behaviour, so an internal compiler error becomes a null result
+    let result = std::panic::catch_unwind(|| {
+        //recovering, so C callers see every diagnostic like the command line does, not just the first
+        let result = compile(&file_name, source, Options { recovery: true, ..Options::default() });
+        //always with line number gutters, so no line inside a diagnostic is blank, even an empty
+        //line of source, and the blank lines between them are unambiguous
+        let diagnostics: Vec<String> = result.diagnostics.iter()
+            .map(|lex_error| lex_error.render(0).trim_end_matches('\n').to_string() + "\n").collect();
+        let diagnostics = diagnostics.join("\n");
+        //rendered diagnostics only contain NULs if the source did, which CStr rules out
+        return AncodeResult { diagnostics: CString::new(diagnostics).unwrap_or_default() };
+    });
+    match result {
+        Ok(result) => return Box::into_raw(Box::new(result)),
+        Err(_) => return std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `result` must be null or a pointer returned by `ancode_compile` that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn ancode_get_diagnostics(result: *const AncodeResult) -> *const c_char {
+    match result.as_ref() {
+        Some(result) => return result.diagnostics.as_ptr(),
+        None => return std::ptr::null(),
+    }
+}
+
+/// # Safety
+/// `result` must be null or a pointer returned by `ancode_compile` that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn ancode_free(result: *mut AncodeResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> String {
+        let file_name = CString::new("my_file").unwrap();
+        let source = CString::new(source).unwrap();
+        unsafe {
+            let result = ancode_compile(file_name.as_ptr(), source.as_ptr());
+            assert!(!result.is_null());
+            let diagnostics = CStr::from_ptr(ancode_get_diagnostics(result)).to_string_lossy().into_owned();
+            ancode_free(result);
+            return diagnostics;
+        }
+    }
+
+    #[test]
+    fn no_diagnostics() {
+        assert_eq!(compile("let x = 5\n"), "");
+    }
+
+    #[test]
+    fn lex_error_diagnostics() {
+        assert!(compile("let x = 0b2\n").starts_with("Error while lexing file my_file"));
+    }
+
+    #[test]
+    fn every_diagnostic() {
+        let diagnostics = compile("let x = 0b2\nlet y = 0b3\n");
+        assert_eq!(diagnostics.matches("Malformed binary literal").count(), 2);
+    }
+
+    #[test]
+    fn separated_by_blank_lines() {
+        //an unclosed string over an empty line, and two unclosed delimiters with labels
+        let diagnostics = compile("((\n\"a\n\nb");
+        assert_eq!(diagnostics.matches("while lexing").count(), 3);
+        assert_eq!(diagnostics.split("\n\n").count(), 3);
+        assert!(diagnostics.ends_with('\n') && !diagnostics.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn null_arguments() {
+        unsafe {
+            assert!(ancode_compile(std::ptr::null(), std::ptr::null()).is_null());
+            assert!(ancode_get_diagnostics(std::ptr::null()).is_null());
+            ancode_free(std::ptr::null_mut());
+        }
+    }
+}
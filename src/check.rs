@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use compiler::{diagnostic, json};
+use compiler::paths;
+use compiler::session::Session;
+
+use crate::ExitStatus;
+
+#[derive(Debug)]
+pub struct CheckSummary {
+    pub files_checked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    //the worst outcome of any file, since errors alone don't say whether it was the user's fault
+    pub status: ExitStatus,
+    //per file error counts, in the order the files were checked
+    pub files: Vec<(String, usize)>,
+}
+
+impl CheckSummary {
+    pub fn exit_status(&self, fail_on_warning: bool) -> ExitStatus {
+        return self.status.failing_on_warnings(self.warnings, fail_on_warning);
+    }
+}
+
+pub fn check_path(path: &str, session: &Session, json_output: bool, context: Option<usize>) -> CheckSummary {
+    let remappings = &session.options().remappings;
+    let mut summary = CheckSummary {
+        files_checked: 0,
+        errors: 0,
+        warnings: 0,
+        status: ExitStatus::Success,
+        files: Vec::new(),
+    };
+    let files = match paths::find_source_files(Path::new(path)) {
+        Ok(files) => files,
+        Err(error) => {
+            let display_name = paths::remap_path(path, remappings);
+            if json_output {
+                eprintln!("{}", json::io_error(&display_name, &error));
+                println!("{}", render_summary_json(&summary));
+            } else {
+                crate::deal_with_file_error(error, display_name);
+            }
+            summary.errors += 1;
+            summary.status = ExitStatus::UsageError;
+            return summary;
+        }
+    };
+
+    for file in files {
+        let file_name = file.to_string_lossy().to_string();
+        let display_name = paths::remap_path(&file_name, remappings);
+        summary.files_checked += 1;
+        let mut file_errors = 0;
+        match crate::read_source_file(&file_name, &display_name) {
+            Ok(source) => {
+                crate::ice::set_phase("lexing", &display_name);
+                session.lex(session.add_source(&file_name, source));
+                let lex_errors = diagnostic::deduplicate(session.take_diagnostics());
+                if !lex_errors.is_empty() {
+                    if !json_output {
+                        println!("==> {}", display_name);
+                    }
+                    crate::print_lex_errors(&lex_errors, json_output, context);
+                    if !json_output {
+                        println!();
+                    }
+                    let warnings = lex_errors.iter().filter(|error| error.error_type().is_warning()).count();
+                    file_errors += lex_errors.len() - warnings;
+                    summary.warnings += warnings;
+                    summary.status = summary.status.max(ExitStatus::from_lex_errors(&lex_errors));
+                }
+            },
+            Err(file_error) => {
+                if json_output {
+                    eprintln!("{}", json::io_error(&display_name, &file_error));
+                } else {
+                    println!("==> {}", display_name);
+                    crate::deal_with_file_error(file_error, display_name.clone());
+                    println!();
+                }
+                file_errors += 1;
+                summary.status = summary.status.max(ExitStatus::UsageError);
+            }
+        }
+        summary.errors += file_errors;
+        summary.files.push((display_name, file_errors));
+    }
+
+    if json_output {
+        println!("{}", render_summary_json(&summary));
+    } else {
+        println!("{}", render_summary(&summary));
+    }
+    return summary;
+}
+
+fn render_summary(summary: &CheckSummary) -> String {
+    return format!("files checked  {}\nerrors         {}\nwarnings       {}",
+        summary.files_checked, summary.errors, summary.warnings);
+}
+
+fn render_summary_json(summary: &CheckSummary) -> String {
+    let files: Vec<String> = summary.files.iter().map(|(file, errors)| json::object(&[
+        ("file", json::string(file)),
+        ("errors", errors.to_string()),
+    ])).collect();
+    return json::object(&[
+        ("files_checked", summary.files_checked.to_string()),
+        ("errors", summary.errors.to_string()),
+        ("warnings", summary.warnings.to_string()),
+        ("files", json::array(&files)),
+    ]);
+}
@@ -0,0 +1,55 @@
+//one call for tools that want everything the compiler produces for a source file. Lexing is the
+//only phase so far, so the result is the tokens and diagnostics; later phases will add their
+//outputs here rather than each having its own entry point and error type.
+use crate::lexer::{LexError, Token};
+use crate::session::{Options, Session};
+
+#[derive(Debug)]
+pub struct CompileResult {
+    pub tokens: Vec<Token>,
+    //every error found, deduplicated, in the order they were found
+    pub diagnostics: Vec<LexError>,
+}
+
+impl CompileResult {
+    //warnings are still in the diagnostics, but like on the command line they don't fail the file
+    pub fn success(&self) -> bool {
+        return !self.diagnostics.iter().any(|diagnostic| !diagnostic.error_type().is_warning());
+    }
+}
+
+//the name is used in diagnostics, after the options' path remappings
+pub fn compile(name: &str, source: String, options: Options) -> CompileResult {
+    let session = Session::new(options);
+    let tokens = session.lex(session.add_source(name, source));
+    return CompileResult { tokens, diagnostics: crate::diagnostic::deduplicate(session.take_diagnostics()) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{LexErrorType, TokenType};
+
+    #[test]
+    fn tokens_and_diagnostics_together() {
+        let result = compile("main.an", "a 0b2 'b\n".into(), Options { recovery: true, ..Options::default() });
+        assert!(!result.success());
+        assert_eq!(result.tokens.first().map(|token| token.kind()), Some(TokenType::Identifier));
+        let errors: Vec<LexErrorType> = result.diagnostics.iter().map(|error| error.error_type()).collect();
+        assert_eq!(errors, vec![LexErrorType::MalformedBinLiteral, LexErrorType::WrongQuotes]);
+        assert_eq!(result.diagnostics[0].file(), "main.an");
+    }
+
+    #[test]
+    fn success() {
+        assert!(compile("main.an", "a = 1\n".into(), Options::default()).success());
+    }
+
+    #[test]
+    fn warnings_only_is_success() {
+        let result = compile("m.an", "x \n".into(), Options::default());
+        assert!(result.success());
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].error_type(), LexErrorType::TrailingWhitespace);
+    }
+}
@@ -0,0 +1,126 @@
+//matches brackets over a finished token stream, so unbalanced delimiters are reported with both
+//ends before anything tries to parse the tokens
+use crate::lexer::{Span, Token, TokenType};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Imbalance {
+    //still open when the file ended
+    Unclosed { kind: TokenType, open: Span, end_of_file: Span },
+    //closes a delimiter further out than the innermost open one, which was left open
+    Mismatched { kind: TokenType, open: Span, close: Span },
+    //closes a kind of delimiter that isn't open at all
+    Unexpected { close: Span },
+}
+
+//the closing token for an opening delimiter
+pub fn closing(kind: TokenType) -> Option<TokenType> {
+    match kind {
+        TokenType::LeftParen => return Some(TokenType::RightParen),
+        TokenType::LeftBrace => return Some(TokenType::RightBrace),
+        TokenType::LeftBracket => return Some(TokenType::RightBracket),
+        _ => return None,
+    }
+}
+
+pub fn is_closing(kind: TokenType) -> bool {
+    return matches!(kind, TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket);
+}
+
+//what an opening delimiter is called in messages
+pub fn name(kind: TokenType) -> &'static str {
+    match kind {
+        TokenType::LeftParen | TokenType::RightParen => return "parenthesis",
+        TokenType::LeftBrace | TokenType::RightBrace => return "brace",
+        _ => return "bracket",
+    }
+}
+
+pub fn check_delimiters(tokens: &[Token]) -> Vec<Imbalance> {
+    let mut open: Vec<(TokenType, Span)> = Vec::new();
+    let mut imbalances = Vec::new();
+    for token in tokens {
+        let kind = token.kind();
+        if closing(kind).is_some() {
+            open.push((kind, token.span()));
+        } else if is_closing(kind) {
+            match open.last() {
+                Some((open_kind, _)) if closing(*open_kind) == Some(kind) => {
+                    open.pop();
+                },
+                Some((open_kind, open_span)) => {
+                    //a delimiter closing one further out means the inner ones were left open.
+                    //Otherwise the stray closing delimiter is skipped, and the open ones are still
+                    //reported at the end of the file if nothing closes them
+                    match open.iter().rposition(|(open_kind, _)| closing(*open_kind) == Some(kind)) {
+                        Some(matching) => {
+                            imbalances.push(Imbalance::Mismatched { kind: *open_kind, open: *open_span, close: token.span() });
+                            open.truncate(matching);
+                        },
+                        None => imbalances.push(Imbalance::Unexpected { close: token.span() }),
+                    }
+                },
+                None => imbalances.push(Imbalance::Unexpected { close: token.span() }),
+            }
+        } else if kind == TokenType::EndOfFile {
+            for (open_kind, open_span) in open.drain(..).rev() {
+                imbalances.push(Imbalance::Unclosed { kind: open_kind, open: open_span, end_of_file: token.span() });
+            }
+        }
+    }
+    return imbalances;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn check(source: &str) -> Vec<Imbalance> {
+        let (tokens, _) = Lexer::new("my_file".into()).with_recovery().lex_with_errors(source.into());
+        return check_delimiters(&tokens);
+    }
+
+    fn span(line: usize, index: usize) -> Span {
+        return Span { start_line: line, end_line: line, start_index: index, end_index: index + 1,
+            start_byte: index, end_byte: index + 1 };
+    }
+
+    #[test]
+    fn balanced() {
+        assert_eq!(check("({[a]} (b))\n"), vec![]);
+    }
+
+    #[test]
+    fn unclosed() {
+        let imbalances = check("{\n(a\n");
+        assert_eq!(imbalances.len(), 2);
+        assert!(matches!(imbalances[0], Imbalance::Unclosed { kind: TokenType::LeftParen, open, .. } if open == span(2, 0)));
+        assert!(matches!(imbalances[1], Imbalance::Unclosed { kind: TokenType::LeftBrace, open, .. } if open == span(1, 0)));
+    }
+
+    #[test]
+    fn stray_closing_delimiter() {
+        //nothing open is a bracket, so the bracket is the only mistake and the paren is still closed
+        assert_eq!(check("(a])\n"), vec![Imbalance::Unexpected { close: span(1, 2) }]);
+    }
+
+    #[test]
+    fn stray_closing_delimiter_not_mismatched() {
+        //the bracket is reported as stray and the paren as unclosed, rather than the paren twice
+        assert!(matches!(check("x = (a]\n")[..],
+            [Imbalance::Unexpected { close }, Imbalance::Unclosed { kind: TokenType::LeftParen, .. }] if close == span(1, 6)));
+        assert!(matches!(check("{\nb)\n")[..],
+            [Imbalance::Unexpected { close }, Imbalance::Unclosed { kind: TokenType::LeftBrace, .. }] if close == span(2, 1)));
+    }
+
+    #[test]
+    fn closes_outer_delimiter() {
+        //the brace is reported once, not again at the end of the file
+        assert_eq!(check("({)\n"), vec![Imbalance::Mismatched { kind: TokenType::LeftBrace, open: span(1, 1), close: span(1, 2) }]);
+    }
+
+    #[test]
+    fn unexpected() {
+        assert_eq!(check("a)\n"), vec![Imbalance::Unexpected { close: span(1, 1) }]);
+    }
+}
@@ -0,0 +1,64 @@
+//delta debugging (Zeller's ddmin) for shrinking inputs that trigger compiler bugs
+use crate::lexer::Lexer;
+
+//the smallest subsequence of units that is still interesting, removing chunks of decreasing size.
+//The full input is assumed to be interesting
+pub fn ddmin<T: Clone>(mut units: Vec<T>, mut is_interesting: impl FnMut(&[T]) -> bool) -> Vec<T> {
+    let mut granularity = 2;
+    while units.len() >= 2 {
+        let chunk_size = units.len().div_ceil(granularity);
+        let mut reduced = false;
+        for start in (0..units.len()).step_by(chunk_size) {
+            let end = (start + chunk_size).min(units.len());
+            let complement: Vec<T> = units[..start].iter().chain(units[end..].iter()).cloned().collect();
+            if is_interesting(&complement) {
+                units = complement;
+                granularity = (granularity - 1).max(2);
+                reduced = true;
+                break;
+            }
+        }
+        if !reduced {
+            if granularity >= units.len() {
+                break;
+            }
+            granularity = (granularity * 2).min(units.len());
+        }
+    }
+    return units;
+}
+
+//splits the source into token texts to reduce over, falling back to characters if the lexer
+//couldn't account for every character (reducing is for inputs the compiler mishandles, after all)
+pub fn reduction_units(source: &str) -> Vec<String> {
+    let (tokens, _) = Lexer::new("reduce".into()).with_recovery().lex_with_errors(source.to_string());
+    let units: Vec<String> = tokens.iter().map(|token| token.text().to_string())
+        .filter(|text| !text.is_empty()).collect();
+    if units.concat() == source {
+        return units;
+    } else {
+        return source.chars().map(|c| c.to_string()).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_minimal_pair() {
+        let units: Vec<u32> = (0..20).collect();
+        let reduced = ddmin(units, |candidate| candidate.contains(&3) && candidate.contains(&17));
+        assert_eq!(reduced, vec![3, 17]);
+    }
+
+    #[test]
+    fn keeps_single_unit() {
+        assert_eq!(ddmin(vec!['a', 'b', 'c'], |candidate| candidate.contains(&'b')), vec!['b']);
+    }
+
+    #[test]
+    fn token_units() {
+        assert_eq!(reduction_units("x = 0b12\n"), vec!["x", " ", "=", " ", "0b12", "\n"]);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::lexer::{LexError, LexErrorType, Span};
+use crate::width;
+
+//how many errors of one type are shown before the rest are summarised
+pub const MAX_SIMILAR_ERRORS: usize = 5;
+
+//a secondary location for a diagnostic, like where an unclosed delimiter was opened
+#[derive(Debug, PartialEq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+//renders the lines the primary span and labels cover with line number gutters and up to
+//`context` lines either side. The primary span is underlined with carets, labels with dashes
+//and their message, and gaps between far apart lines are shown as "..."
+pub fn render_snippet(source: &str, span: &Span, labels: &[Label], context: usize) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut ranges: Vec<(usize, usize)> = std::iter::once(span).chain(labels.iter().map(|label| &label.span))
+        .map(|span| (span.start_line.saturating_sub(context).max(1),
+            (span.end_line + context).min(lines.len().max(span.end_line))))
+        .collect();
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (first, last) in ranges {
+        match merged.last_mut() {
+            Some(previous) if first <= previous.1 + 1 => previous.1 = previous.1.max(last),
+            _ => merged.push((first, last)),
+        }
+    }
+    let gutter = merged.last().map(|range| range.1).unwrap_or_default().to_string().len();
+
+    let mut rendered = String::new();
+    for (range_num, (first, last)) in merged.into_iter().enumerate() {
+        if range_num > 0 {
+            rendered += &format!("{:>gutter$}\n", "...");
+        }
+        for line_num in first..=last {
+            let line = lines.get(line_num - 1).copied().unwrap_or_default();
+            rendered += &format!("{:>gutter$} | {}\n", line_num, line);
+            if span.start_line == span.end_line && line_num == span.start_line {
+                rendered += &format!("{:>gutter$} | {}\n", "", underline(line, &at_least_one_char(span)));
+            }
+            for label in labels.iter().filter(|label| label.span.start_line == line_num) {
+                //a label running onto later lines is marked from where it starts to the end of the line
+                let mut label_span = label.span;
+                if label_span.end_line != label_span.start_line {
+                    label_span.end_index = line.chars().count();
+                }
+                let marks = underline(line, &at_least_one_char(&label_span)).replace('^', "-");
+                rendered += &format!("{:>gutter$} | {} {}\n", "", marks, label.message);
+            }
+        }
+    }
+    return rendered;
+}
+
+//the same error reported twice at the same place is only kept once. Errors at the same span but
+//of a different kind or pointing at a different opener (e.g. several unclosed delimiters all
+//ending at the end of the file) are separate problems, so they're all kept
+pub fn deduplicate(errors: Vec<LexError>) -> Vec<LexError> {
+    let mut seen: Vec<(LexErrorType, Span, Vec<Label>)> = Vec::new();
+    let mut kept = Vec::new();
+    for error in errors {
+        let key = (error.error_type(), error.span(), error.labels().to_vec());
+        if !seen.contains(&key) {
+            seen.push(key);
+            kept.push(error);
+        }
+    }
+    return kept;
+}
+
+//the first `max_similar` errors of each type, and how many more of each type there were in the
+//order the types first appeared
+pub fn group_similar(errors: &[LexError], max_similar: usize) -> (Vec<&LexError>, Vec<(LexErrorType, usize)>) {
+    let mut counts: Vec<(LexErrorType, usize)> = Vec::new();
+    let mut shown = Vec::new();
+    for error in errors {
+        let index = match counts.iter().position(|(error_type, _)| *error_type == error.error_type()) {
+            Some(index) => index,
+            None => {
+                counts.push((error.error_type(), 0));
+                counts.len() - 1
+            }
+        };
+        counts[index].1 += 1;
+        if counts[index].1 <= max_similar {
+            shown.push(error);
+        }
+    }
+    let hidden = counts.into_iter().filter(|(_, count)| *count > max_similar)
+        .map(|(error_type, count)| (error_type, count - max_similar)).collect();
+    return (shown, hidden);
+}
+
+//so an empty span, like one at the end of the file, still gets a mark
+fn at_least_one_char(span: &Span) -> Span {
+    let mut span = *span;
+    span.end_index = span.end_index.max(span.start_index + 1);
+    return span;
+}
+
+//measured in display width rather than characters, so wide characters don't misalign it. Tabs
+//before the span are copied, so they expand to the same tab stop as in the echoed line
+pub fn underline(line: &str, span: &Span) -> String {
+    let padding: String = line.chars().take(span.start_index)
+        .map(|c| if c == '\t' { "\t".to_string() } else { " ".repeat(width::char_width(c)) }).collect();
+    let underlined: String = line.chars().skip(span.start_index)
+        .take(span.end_index - span.start_index).collect();
+    //the span can run past the end of the line, e.g. for a missing trailing newline
+    let missing = (span.end_index - span.start_index).saturating_sub(underlined.chars().count());
+    return padding + &"^".repeat(width::str_width(&underlined) + missing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: usize, start_index: usize, end_index: usize) -> Span {
+        return Span { start_line: line, end_line: line, start_index, end_index,
+            start_byte: start_index, end_byte: end_index };
+    }
+
+    fn errors(source: &str) -> Vec<LexError> {
+        return crate::lexer::Lexer::new("my_file".into()).with_recovery().lex_with_errors(source.into()).1;
+    }
+
+    #[test]
+    fn groups_similar_errors() {
+        let errors = errors("0b2 0b2 0b2 'a\n");
+        let (shown, hidden) = group_similar(&errors, 2);
+        let shown: Vec<LexErrorType> = shown.iter().map(|error| error.error_type()).collect();
+        assert_eq!(shown, vec![LexErrorType::MalformedBinLiteral, LexErrorType::MalformedBinLiteral, LexErrorType::WrongQuotes]);
+        assert_eq!(hidden, vec![(LexErrorType::MalformedBinLiteral, 1)]);
+    }
+
+    #[test]
+    fn drops_errors_at_the_same_span() {
+        let mut duplicated = errors("0b2\n");
+        duplicated.extend(errors("0b2\n"));
+        assert_eq!(deduplicate(duplicated).len(), 1);
+    }
+
+    #[test]
+    fn keeps_each_unclosed_delimiter() {
+        let unclosed = deduplicate(errors("((((\n"));
+        assert_eq!(unclosed.len(), 4);
+        assert!(unclosed.iter().all(|error| error.error_type() == LexErrorType::UnclosedDelimiter));
+    }
+
+    #[test]
+    fn tabs_before_the_error_kept() {
+        let line = "\tx = \t0b2";
+        assert_eq!(underline(line, &span(1, 6, 9)), "\t    \t^^^");
+    }
+
+    #[test]
+    fn context_lines() {
+        let source = "a\nb\nc 0b2\nd\ne\n";
+        assert_eq!(render_snippet(source, &span(3, 2, 5), &[], 1), "2 | b\n3 | c 0b2\n  |   ^^^\n4 | d\n");
+    }
+
+    #[test]
+    fn context_clamped_to_file() {
+        let source = "0b2\nb\n";
+        assert_eq!(render_snippet(source, &span(1, 0, 3), &[], 5), "1 | 0b2\n  | ^^^\n2 | b\n");
+    }
+
+    #[test]
+    fn gutter_fits_widest_line_number() {
+        let source = "x\n".repeat(9) + "0b2\n";
+        assert_eq!(render_snippet(&source, &span(10, 0, 3), &[], 1), " 9 | x\n10 | 0b2\n   | ^^^\n");
+    }
+
+    #[test]
+    fn secondary_label_on_another_line() {
+        let source = "(a\nb\nc\nd\n";
+        let label = Label { span: span(1, 0, 1), message: "unclosed parenthesis opened here".into() };
+        assert_eq!(render_snippet(source, &span(4, 1, 1), &[label], 0),
+            "1 | (a\n  | - unclosed parenthesis opened here\n...\n4 | d\n  |  ^\n");
+    }
+
+    #[test]
+    fn labels_on_the_same_line() {
+        let source = "(a}\n";
+        let label = Label { span: span(1, 0, 1), message: "opened here".into() };
+        assert_eq!(render_snippet(source, &span(1, 2, 3), &[label], 0),
+            "1 | (a}\n  |   ^\n  | - opened here\n");
+    }
+}
@@ -0,0 +1,182 @@
+//structural comparison of two token streams, ignoring layout and comments
+use std::collections::HashMap;
+
+use crate::lexer::{Token, TokenType};
+
+#[derive(Debug)]
+pub enum DiffOp<'a> {
+    Same(&'a Token, &'a Token),
+    Removed(&'a Token),
+    Added(&'a Token),
+}
+
+//whitespace and comments can change without changing the code. Newlines end statements so they
+//count, but a run of them is one newline since blank and comment-only lines don't end anything
+fn significant_tokens(tokens: &[Token]) -> Vec<&Token> {
+    let mut significant: Vec<&Token> = Vec::new();
+    for token in tokens {
+        let previous_ended_line = significant.last().is_none_or(|last| last.kind() == TokenType::Newline);
+        let skipped = match token.kind() {
+            TokenType::EndOfFile => true,
+            TokenType::Newline => previous_ended_line,
+            _ => token.is_trivia(),
+        };
+        if !skipped {
+            significant.push(token);
+        }
+    }
+    return significant;
+}
+
+//a longest common subsequence over the significant tokens. The unchanged start and end are
+//matched directly, and the rest uses Hirschberg's algorithm so memory stays linear in the
+//length of the files rather than their product
+pub fn diff_tokens<'a>(old: &'a [Token], new: &'a [Token]) -> Vec<DiffOp<'a>> {
+    let old = significant_tokens(old);
+    let new = significant_tokens(new);
+    //tokens are the same if their kind and text are, so each distinct pair gets a number and the
+    //inner loops compare those instead of strings
+    let mut ids: HashMap<(&str, &str), u32> = HashMap::new();
+    let mut id_of = |token: &&'a Token| {
+        let next = ids.len() as u32;
+        return *ids.entry((token.kind().name(), token.text())).or_insert(next);
+    };
+    let old_ids: Vec<u32> = old.iter().map(&mut id_of).collect();
+    let new_ids: Vec<u32> = new.iter().map(&mut id_of).collect();
+
+    let prefix = old_ids.iter().zip(&new_ids).take_while(|(old, new)| old == new).count();
+    let suffix = old_ids[prefix..].iter().rev().zip(new_ids[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new).count();
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Same(i, i)).collect();
+    diff_middle(&old_ids[prefix..old.len() - suffix], &new_ids[prefix..new.len() - suffix], prefix, prefix, &mut edits);
+    edits.extend((0..suffix).map(|i| Edit::Same(old.len() - suffix + i, new.len() - suffix + i)));
+    return edits.iter().map(|edit| match *edit {
+        Edit::Same(i, j) => DiffOp::Same(old[i], new[j]),
+        Edit::Removed(i) => DiffOp::Removed(old[i]),
+        Edit::Added(j) => DiffOp::Added(new[j]),
+    }).collect();
+}
+
+//a DiffOp by index into the significant tokens
+enum Edit {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+//old and new start at old_start and new_start in the full token lists
+fn diff_middle(old: &[u32], new: &[u32], old_start: usize, new_start: usize, edits: &mut Vec<Edit>) {
+    if old.is_empty() || new.is_empty() {
+        edits.extend((0..old.len()).map(|i| Edit::Removed(old_start + i)));
+        edits.extend((0..new.len()).map(|j| Edit::Added(new_start + j)));
+        return;
+    }
+    if let [only] = old {
+        match new.iter().position(|token| token == only) {
+            Some(matching) => {
+                edits.extend((0..matching).map(|j| Edit::Added(new_start + j)));
+                edits.push(Edit::Same(old_start, new_start + matching));
+                edits.extend((matching + 1..new.len()).map(|j| Edit::Added(new_start + j)));
+            },
+            None => {
+                edits.push(Edit::Removed(old_start));
+                edits.extend((0..new.len()).map(|j| Edit::Added(new_start + j)));
+            },
+        }
+        return;
+    }
+
+    //split old in half, and new wherever the two halves' LCS lengths add up to the most
+    let middle = old.len() / 2;
+    let forward = lcs_lengths(&old[..middle], new);
+    let reversed_old: Vec<u32> = old[middle..].iter().rev().copied().collect();
+    let reversed_new: Vec<u32> = new.iter().rev().copied().collect();
+    let backward = lcs_lengths(&reversed_old, &reversed_new);
+    let mut split = 0;
+    for j in 0..=new.len() {
+        if forward[j] + backward[new.len() - j] > forward[split] + backward[new.len() - split] {
+            split = j;
+        }
+    }
+    diff_middle(&old[..middle], &new[..split], old_start, new_start, edits);
+    diff_middle(&old[middle..], &new[split..], old_start + middle, new_start + split, edits);
+}
+
+//the LCS length of all of old against each prefix of new, keeping only one row of the table
+fn lcs_lengths(old: &[u32], new: &[u32]) -> Vec<usize> {
+    let mut row = vec![0usize; new.len() + 1];
+    for old_token in old {
+        let mut diagonal = 0;
+        for (j, new_token) in new.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if old_token == new_token { diagonal + 1 } else { above.max(row[j]) };
+            diagonal = above;
+        }
+    }
+    return row;
+}
+
+pub fn has_changes(ops: &[DiffOp]) -> bool {
+    return ops.iter().any(|op| !matches!(op, DiffOp::Same(_, _)));
+}
+
+pub fn render_diff(ops: &[DiffOp]) -> String {
+    let mut rendered = String::new();
+    for op in ops {
+        let (sign, token) = match op {
+            DiffOp::Same(_, _) => continue,
+            DiffOp::Removed(token) => ('-', token),
+            DiffOp::Added(token) => ('+', token),
+        };
+        let span = token.span();
+        rendered += &format!("{} {}:{}  {}\n", sign, span.start_line, span.start_index, token);
+    }
+    return rendered;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn lex(source: &str) -> Vec<Token> {
+        return Lexer::new("my_file".into()).lex(source.into()).expect("Unexpected error during test");
+    }
+
+    #[test]
+    fn ignores_layout_and_comments() {
+        let old = lex("x = 1 + 2\n");
+        let new = lex("// sum\nx  =  1+2\n\n");
+        assert!(!has_changes(&diff_tokens(&old, &new)));
+    }
+
+    #[test]
+    fn line_splits_are_changes() {
+        let old = lex("a = b\nc\n");
+        let new = lex("a = b c\n");
+        assert!(has_changes(&diff_tokens(&old, &new)));
+        assert!(!has_changes(&diff_tokens(&old, &lex("a = b\n\n// note\nc\n"))));
+    }
+
+    #[test]
+    fn matches_a_full_lcs() {
+        let old = lex("a b c d e f\n");
+        let new = lex("b x d e y f\n");
+        let rendered: Vec<String> = diff_tokens(&old, &new).iter().map(|op| match op {
+            DiffOp::Same(token, _) => format!(" {}", token.text()),
+            DiffOp::Removed(token) => format!("-{}", token.text()),
+            DiffOp::Added(token) => format!("+{}", token.text()),
+        }).collect();
+        assert_eq!(rendered, vec!["-a", " b", "-c", "+x", " d", " e", "+y", " f", " \n"]);
+    }
+
+    #[test]
+    fn reports_changed_token() {
+        let old = lex("x = 1 + 2\n");
+        let new = lex("x = 1 + 3\n");
+        assert!(has_changes(&diff_tokens(&old, &new)));
+        assert_eq!(render_diff(&diff_tokens(&old, &new)),
+            "- 1:8  Decimal literal: \"2\"\n+ 1:8  Decimal literal: \"3\"\n");
+    }
+}
@@ -0,0 +1,112 @@
+//file-level directives written as `//! ancode: name(arguments)` comments. `allow(name, ...)`
+//relaxes the lexer's style checks for that file, adding to anything allowed through
+//Lexer::allow, so a file can opt out of a check but can't turn back on one its caller allowed.
+//`edition(version)` pins the language version the file was written for, and `feature(name, ...)`
+//turns on unstable features. Directives only count in the comments at the top of the file, so
+//text that looks like one inside a string literal, or anywhere after the first line of code,
+//doesn't change how the file is lexed.
+use crate::edition::LanguageVersion;
+use crate::features::UnstableFeature;
+use crate::lexer::LexErrorType;
+
+pub const DIRECTIVE_PREFIX: &str = "//! ancode:";
+
+//the errors that are style choices rather than code the lexer can't understand
+pub const ALLOWABLE_ERRORS: &[LexErrorType] = &[
+    LexErrorType::TrailingWhitespace,
+    LexErrorType::MissingTrailingNewLine,
+];
+
+const DIRECTIVE_NAMES: &[&str] = &["allow", "edition", "feature"];
+
+//each directive's line number, name and arguments, from the blank and comment lines that
+//start the file. No string can have started yet on those lines, unlike anywhere later
+fn directives(source: &str) -> impl Iterator<Item = (usize, &str, &str)> {
+    let header = source.lines().enumerate()
+        .take_while(|(_, line)| line.trim().is_empty() || line.trim_start().starts_with("//"));
+    return header.filter_map(|(line_num, line)| {
+        let directive = line.trim_start().strip_prefix(DIRECTIVE_PREFIX)?.trim();
+        let parsed = directive.split_once('(')
+            .and_then(|(name, rest)| Some((name.trim(), rest.strip_suffix(')')?)))
+            .filter(|(name, _)| DIRECTIVE_NAMES.contains(name));
+        if parsed.is_none() {
+            crate::info!("ignoring unknown directive '{}'", directive);
+        }
+        let (name, arguments) = parsed?;
+        return Some((line_num + 1, name, arguments));
+    });
+}
+
+pub fn allowed_errors(source: &str) -> Vec<LexErrorType> {
+    let mut allowed = Vec::new();
+    for (_, _, names) in directives(source).filter(|(_, name, _)| *name == "allow") {
+        for name in names.split(',').map(str::trim) {
+            match ALLOWABLE_ERRORS.iter().find(|error_type| error_type.name() == name) {
+                Some(error_type) => allowed.push(*error_type),
+                None => crate::info!("ignoring allow of unknown or required check '{}'", name),
+            }
+        }
+    }
+    return allowed;
+}
+
+//the edition the file pins, if any. An edition the compiler doesn't know is an error on that
+//line rather than being ignored, since the file would be lexed with the wrong rules
+pub fn edition(source: &str) -> Result<Option<LanguageVersion>, usize> {
+    let mut edition = None;
+    for (line_num, _, version) in directives(source).filter(|(_, name, _)| *name == "edition") {
+        match version.trim().parse() {
+            Ok(version) => edition = Some(version),
+            Err(_) => return Err(line_num),
+        }
+    }
+    return Ok(edition);
+}
+
+//each feature the file turns on with the line it was turned on, and its name as written in case
+//it isn't one the compiler knows
+pub fn features(source: &str) -> Vec<(usize, &str, Option<UnstableFeature>)> {
+    let mut features = Vec::new();
+    for (line_num, _, names) in directives(source).filter(|(_, name, _)| *name == "feature") {
+        for name in names.split(',').map(str::trim) {
+            features.push((line_num, name, name.parse().ok()));
+        }
+    }
+    return features;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_allow() {
+        assert_eq!(allowed_errors("//! ancode: allow(trailing_whitespace, missing_trailing_newline)\nx\n"),
+            vec![LexErrorType::TrailingWhitespace, LexErrorType::MissingTrailingNewLine]);
+    }
+
+    #[test]
+    fn parses_edition() {
+        assert_eq!(edition("//! ancode: edition(0.1)\nx\n"), Ok(Some(LanguageVersion::V0_1)));
+        assert_eq!(edition("x\n"), Ok(None));
+        assert_eq!(edition("// about this file\n//! ancode: edition(9.9)\n"), Err(2));
+    }
+
+    #[test]
+    fn parses_features() {
+        assert_eq!(features("//! ancode: feature(indentation, macros)\n"),
+            vec![(1, "indentation", Some(UnstableFeature::Indentation)), (1, "macros", None)]);
+    }
+
+    #[test]
+    fn only_read_from_leading_comments() {
+        assert_eq!(allowed_errors("x = \"\n//! ancode: allow(trailing_whitespace)\n\"\ny \n"), vec![]);
+        assert_eq!(allowed_errors("x\n//! ancode: allow(trailing_whitespace)\n"), vec![]);
+        assert_eq!(features("\n// notes\n//! ancode: feature(indentation)\nx\n").len(), 1);
+    }
+
+    #[test]
+    fn ignores_other_comments_and_required_checks() {
+        assert_eq!(allowed_errors("// ancode: allow(trailing_whitespace)\n//! ancode: allow(wrong_quotes)\n"), vec![]);
+    }
+}
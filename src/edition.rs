@@ -0,0 +1,52 @@
+//versions of the language, so a project can pin the behaviour it was written for while the
+//language changes. There is only one so far, but the lexer already takes it as a parameter so
+//later versions can change tokens without breaking old code.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub enum LanguageVersion {
+    #[default]
+    V0_1,
+}
+
+impl LanguageVersion {
+    pub const ALL: &'static [LanguageVersion] = &[LanguageVersion::V0_1];
+    pub const LATEST: LanguageVersion = LanguageVersion::V0_1;
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LanguageVersion::V0_1 => return "0.1",
+        }
+    }
+}
+
+impl std::fmt::Display for LanguageVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl std::str::FromStr for LanguageVersion {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<LanguageVersion, String> {
+        match LanguageVersion::ALL.iter().find(|version| version.name() == name) {
+            Some(version) => return Ok(*version),
+            None => {
+                let known: Vec<&str> = LanguageVersion::ALL.iter().map(LanguageVersion::name).collect();
+                return Err(format!("unknown edition '{}', expected one of: {}", name, known.join(", ")));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_names() {
+        for version in LanguageVersion::ALL {
+            assert_eq!(version.name().parse::<LanguageVersion>(), Ok(*version));
+        }
+        assert!("0.9".parse::<LanguageVersion>().is_err());
+    }
+}
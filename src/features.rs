@@ -0,0 +1,51 @@
+//experimental language features, which a file turns on with a `//! ancode: feature(name)`
+//directive. Using one is an error unless the compiler was run with `--unstable-feature name`,
+//so new syntax can be tried out without anyone depending on it by accident.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnstableFeature {
+    //blocks by indentation, see Lexer::with_indentation
+    Indentation,
+}
+
+impl UnstableFeature {
+    pub const ALL: &'static [UnstableFeature] = &[UnstableFeature::Indentation];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UnstableFeature::Indentation => return "indentation",
+        }
+    }
+}
+
+impl std::fmt::Display for UnstableFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl std::str::FromStr for UnstableFeature {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<UnstableFeature, String> {
+        match UnstableFeature::ALL.iter().find(|feature| feature.name() == name) {
+            Some(feature) => return Ok(*feature),
+            None => {
+                let known: Vec<&str> = UnstableFeature::ALL.iter().map(UnstableFeature::name).collect();
+                return Err(format!("unknown unstable feature '{}', expected one of: {}", name, known.join(", ")));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_names() {
+        for feature in UnstableFeature::ALL {
+            assert_eq!(feature.name().parse::<UnstableFeature>(), Ok(*feature));
+        }
+        assert!("macros".parse::<UnstableFeature>().is_err());
+    }
+}
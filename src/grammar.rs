@@ -0,0 +1,185 @@
+//a declarative description of the token grammar, kept in sync with the hand-written lexer
+//by the tests at the bottom of this file. There is no parser yet, so only tokens are described.
+//IgnoredNewline isn't listed, since it is a Newline that the lexer reclassifies inside brackets,
+//and neither are Indent and Dedent, which come from the indentation of each line.
+use crate::json;
+use crate::lexer::{Operator, TokenType};
+
+pub struct TokenDefinition {
+    pub token_type: TokenType,
+    pub ebnf: &'static str,
+    pub regex: &'static str,
+    pub example: &'static str,
+}
+
+pub const HELPER_RULES: &[(&str, &str)] = &[
+    ("bit", "\"0\" | \"1\""),
+    ("digit", "\"0\" | \"1\" | \"2\" | \"3\" | \"4\" | \"5\" | \"6\" | \"7\" | \"8\" | \"9\""),
+    ("hex_digit", "digit | \"a\" | \"b\" | \"c\" | \"d\" | \"e\" | \"f\""),
+    ("ascii_letter", "? A-Z or a-z ?"),
+    ("letter", "? any alphabetic unicode character ?"),
+    ("escape", "\"\\\", ( \"n\" | \"t\" | \"r\" | \"0\" | \"\\\" | '\"' )"),
+    ("string_char", "? any character except '\"' and \"\\\" ?"),
+    ("comment_char", "? any character except newline ?"),
+];
+
+pub const TOKEN_DEFINITIONS: &[TokenDefinition] = &[
+    TokenDefinition { token_type: TokenType::BinLiteral,
+        ebnf: "\"0b\", bit, { bit }", regex: "0b[01]+", example: "0b1010" },
+    TokenDefinition { token_type: TokenType::HexLiteral,
+        ebnf: "\"0x\", hex_digit, { hex_digit }", regex: "0x[0-9a-f]+", example: "0xbeef" },
+    TokenDefinition { token_type: TokenType::DecimalLiteral,
+        ebnf: "digit, { digit }, [ \".\", digit, { digit } ]", regex: "[0-9]+(\\.[0-9]+)?", example: "3.14" },
+    TokenDefinition { token_type: TokenType::StringLiteral,
+        ebnf: "'\"', { string_char | escape }, '\"'", regex: "\"([^\"\\\\]|\\\\[ntr0\\\\\"])*\"", example: "\"a\\n\"" },
+    TokenDefinition { token_type: TokenType::LineComment,
+        ebnf: "\"//\", { comment_char }", regex: "//.*$", example: "// note" },
+    TokenDefinition { token_type: TokenType::Operator(Operator::Equals),
+        ebnf: "\"==\"", regex: "==", example: "==" },
+    TokenDefinition { token_type: TokenType::Operator(Operator::Plus),
+        ebnf: "\"+\"", regex: "\\+", example: "+" },
+    TokenDefinition { token_type: TokenType::Operator(Operator::Minus),
+        ebnf: "\"-\"", regex: "-", example: "-" },
+    TokenDefinition { token_type: TokenType::Operator(Operator::Multiply),
+        ebnf: "\"*\"", regex: "\\*", example: "*" },
+    TokenDefinition { token_type: TokenType::Operator(Operator::Divide),
+        ebnf: "\"/\"", regex: "/", example: "/" },
+    TokenDefinition { token_type: TokenType::Equals,
+        ebnf: "\"=\"", regex: "=", example: "=" },
+    TokenDefinition { token_type: TokenType::LeftParen,
+        ebnf: "\"(\"", regex: "\\(", example: "(" },
+    TokenDefinition { token_type: TokenType::RightParen,
+        ebnf: "\")\"", regex: "\\)", example: ")" },
+    TokenDefinition { token_type: TokenType::LeftBrace,
+        ebnf: "\"{\"", regex: "\\{", example: "{" },
+    TokenDefinition { token_type: TokenType::RightBrace,
+        ebnf: "\"}\"", regex: "\\}", example: "}" },
+    TokenDefinition { token_type: TokenType::LeftBracket,
+        ebnf: "\"[\"", regex: "\\[", example: "[" },
+    TokenDefinition { token_type: TokenType::RightBracket,
+        ebnf: "\"]\"", regex: "\\]", example: "]" },
+    TokenDefinition { token_type: TokenType::Identifier,
+        ebnf: "ascii_letter, { letter }", regex: "[A-Za-z][[:alpha:]]*", example: "name" },
+    TokenDefinition { token_type: TokenType::Whitespace,
+        ebnf: "\" \", { \" \" }", regex: " +", example: "  " },
+    TokenDefinition { token_type: TokenType::Newline,
+        ebnf: "? newline ?", regex: "\\n", example: "\n" },
+    TokenDefinition { token_type: TokenType::LineContinuation,
+        ebnf: "\"\\\", ? newline ?", regex: "\\\\\\n", example: "\\\n" },
+];
+
+pub fn to_ebnf() -> String {
+    let mut ebnf = String::from("(* AnCode token grammar *)\n");
+    for definition in TOKEN_DEFINITIONS {
+        ebnf += &format!("{} = {} ;\n", definition.token_type.name(), definition.ebnf);
+    }
+    ebnf += "\n";
+    for (name, rule) in HELPER_RULES {
+        ebnf += &format!("{} = {} ;\n", name, rule);
+    }
+    return ebnf;
+}
+
+pub fn to_json() -> String {
+    let tokens: Vec<String> = TOKEN_DEFINITIONS.iter().map(|definition| json::object(&[
+        ("name", json::string(definition.token_type.name())),
+        ("ebnf", json::string(definition.ebnf)),
+        ("regex", json::string(definition.regex)),
+    ])).collect();
+    let rules: Vec<String> = HELPER_RULES.iter().map(|(name, rule)| json::object(&[
+        ("name", json::string(name)),
+        ("ebnf", json::string(rule)),
+    ])).collect();
+    return json::object(&[("tokens", json::array(&tokens)), ("rules", json::array(&rules))]) + "\n";
+}
+
+//TextMate scope for editors, or None for tokens that aren't highlighted
+pub fn textmate_scope(token_type: TokenType) -> Option<&'static str> {
+    match token_type {
+        TokenType::BinLiteral => Some("constant.numeric.binary.ancode"),
+        TokenType::HexLiteral => Some("constant.numeric.hex.ancode"),
+        TokenType::DecimalLiteral => Some("constant.numeric.decimal.ancode"),
+        TokenType::StringLiteral => Some("string.quoted.double.ancode"),
+        TokenType::LineComment => Some("comment.line.double-slash.ancode"),
+        TokenType::Operator(Operator::Equals) => Some("keyword.operator.comparison.ancode"),
+        TokenType::Operator(_) => Some("keyword.operator.arithmetic.ancode"),
+        TokenType::Equals => Some("keyword.operator.assignment.ancode"),
+        TokenType::LeftParen | TokenType::RightParen => Some("punctuation.parenthesis.ancode"),
+        TokenType::LeftBrace | TokenType::RightBrace => Some("punctuation.brace.ancode"),
+        TokenType::LeftBracket | TokenType::RightBracket => Some("punctuation.bracket.ancode"),
+        TokenType::Identifier => Some("variable.other.ancode"),
+        TokenType::LineContinuation => Some("punctuation.separator.continuation.ancode"),
+        TokenType::Whitespace | TokenType::Newline | TokenType::IgnoredNewline |
+        TokenType::Indent | TokenType::Dedent | TokenType::EndOfFile | TokenType::Error => None,
+    }
+}
+
+//a TextMate grammar (as used by VS Code, Sublime and others), with one pattern per token in
+//TOKEN_DEFINITIONS order, which already puts longer tokens like == before their prefixes
+pub fn to_textmate() -> String {
+    let mut patterns = Vec::new();
+    for definition in TOKEN_DEFINITIONS {
+        if let Some(scope) = textmate_scope(definition.token_type) {
+            let pattern = match definition.token_type {
+                TokenType::StringLiteral => json::object(&[
+                    ("name", json::string(scope)),
+                    ("begin", json::string("\"")),
+                    ("end", json::string("\"")),
+                    ("patterns", json::array(&[json::object(&[
+                        ("name", json::string("constant.character.escape.ancode")),
+                        ("match", json::string("\\\\[ntr0\\\\\"]")),
+                    ])])),
+                ]),
+                _ => json::object(&[
+                    ("name", json::string(scope)),
+                    ("match", json::string(definition.regex)),
+                ]),
+            };
+            patterns.push(pattern);
+        }
+    }
+    return json::object(&[
+        ("name", json::string("AnCode")),
+        ("scopeName", json::string("source.ancode")),
+        ("fileTypes", json::array(&[json::string(crate::paths::SOURCE_EXTENSION)])),
+        ("patterns", json::array(&patterns)),
+    ]) + "\n";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{LexErrorType, Lexer};
+
+    #[test]
+    fn examples_lex_to_their_token() {
+        for definition in TOKEN_DEFINITIONS {
+            //wrapped in braces so trailing whitespace and missing newlines aren't an issue,
+            //though a comment will swallow the closing brace and delimiters unbalance them
+            let source = format!("{{{}}}\n", definition.example);
+            let (tokens, errors) = Lexer::new("my_file".into()).with_recovery().lex_with_errors(source);
+            assert!(errors.iter().all(|error| matches!(error.error_type(),
+                LexErrorType::UnclosedDelimiter | LexErrorType::MismatchedDelimiter |
+                LexErrorType::UnexpectedClosingDelimiter)), "Example failed to lex");
+            assert_eq!(tokens[1].kind().name(), definition.token_type.name(),
+                "example for {} lexed differently", definition.token_type.name());
+            assert!(tokens[1].text().starts_with(definition.example));
+        }
+    }
+
+    #[test]
+    fn textmate_patterns() {
+        let textmate = to_textmate();
+        assert!(textmate.starts_with("{\"name\":\"AnCode\",\"scopeName\":\"source.ancode\""));
+        assert!(textmate.contains("{\"name\":\"constant.numeric.binary.ancode\",\"match\":\"0b[01]+\"}"));
+        assert!(textmate.contains("\"begin\":\"\\\"\""));
+        assert!(!textmate.contains(" +"));
+    }
+
+    #[test]
+    fn ebnf_rule_per_token() {
+        let ebnf = to_ebnf();
+        assert!(ebnf.contains("bin_literal = \"0b\", bit, { bit } ;\n"));
+        assert_eq!(ebnf.lines().filter(|line| line.ends_with(';')).count(), TOKEN_DEFINITIONS.len() + HELPER_RULES.len());
+    }
+}
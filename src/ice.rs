@@ -0,0 +1,29 @@
+use std::sync::Mutex;
+
+const BUG_REPORT_URL: &str = "https://github.com/spacejoey86/AnCode/issues";
+
+//the phase and file being processed, so a panic can say what the compiler was doing
+static CURRENT_PHASE: Mutex<Option<(&'static str, String)>> = Mutex::new(None);
+
+pub fn set_phase(phase: &'static str, file: &str) {
+    if let Ok(mut current) = CURRENT_PHASE.lock() {
+        *current = Some((phase, file.to_string()));
+    }
+}
+
+//replaces the default panic output with a report asking the user to file a bug
+pub fn install_hook() {
+    std::panic::set_hook(Box::new(|panic_info| {
+        eprintln!("error: internal compiler error: the compiler unexpectedly panicked. This is a bug.");
+        eprintln!("note: compiler version {}", compiler::VERSION);
+        //try_lock, since the panic may have happened while the phase was being updated
+        if let Ok(current) = CURRENT_PHASE.try_lock() {
+            if let Some((phase, file)) = current.as_ref() {
+                eprintln!("note: while {} file '{}'", phase, file);
+            }
+        }
+        eprintln!("note: {}", panic_info);
+        eprintln!("note: please file a bug report with the file that caused this at {}", BUG_REPORT_URL);
+        std::process::exit(crate::ExitStatus::InternalError as i32);
+    }));
+}
@@ -0,0 +1,146 @@
+//the offside rule: works out where Indent and Dedent tokens go from the leading whitespace of
+//each line, for experimenting with blocks by indentation. Lines that are blank or only a comment
+//don't change the indentation, and neither do lines continued inside brackets or after a backslash.
+use crate::lexer::{Span, Token, TokenType};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IndentationIssue {
+    //tabs and spaces in the same line's indentation
+    Mixed { indentation: Span },
+    //indented with tabs in a file that started with spaces, or the other way round
+    Inconsistent { indentation: Span, first: Span, first_uses_tabs: bool },
+    //dedented to a width that none of the enclosing blocks used
+    UnmatchedDedent { indentation: Span },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Indentation {
+    //Indent and Dedent tokens to insert before the token at the index, in order
+    pub changes: Vec<(usize, TokenType, Span)>,
+    pub issues: Vec<IndentationIssue>,
+}
+
+//an empty span where the token starts, since Indent and Dedent don't cover any text
+fn start_of(token: &Token) -> Span {
+    let span = token.span();
+    return Span { start_line: span.start_line, end_line: span.start_line,
+        start_index: span.start_index, end_index: span.start_index,
+        start_byte: span.start_byte, end_byte: span.start_byte };
+}
+
+pub fn check_indentation(tokens: &[Token]) -> Indentation {
+    let mut indentation = Indentation { changes: Vec::new(), issues: Vec::new() };
+    //widths of the enclosing blocks, innermost last
+    let mut levels: Vec<usize> = vec![0];
+    //whether the file is indented with tabs, and where that was decided
+    let mut style: Option<(bool, Span)> = None;
+    let mut line_start = true;
+    let mut leading: Option<&Token> = None;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let kind = token.kind();
+        if kind == TokenType::EndOfFile {
+            while levels.len() > 1 {
+                levels.pop();
+                indentation.changes.push((index, TokenType::Dedent, start_of(token)));
+            }
+            break;
+        }
+        if line_start && kind == TokenType::Whitespace {
+            leading = Some(token);
+            continue;
+        }
+        if line_start && !matches!(kind, TokenType::Newline | TokenType::LineComment) {
+            let text = leading.map(|leading| leading.text()).unwrap_or_default();
+            let span = leading.map(|leading| leading.span()).unwrap_or_else(|| start_of(token));
+            if text.contains(' ') && text.contains('\t') {
+                indentation.issues.push(IndentationIssue::Mixed { indentation: span });
+            } else if !text.is_empty() {
+                let uses_tabs = text.contains('\t');
+                match style {
+                    None => style = Some((uses_tabs, span)),
+                    Some((first_uses_tabs, first)) if first_uses_tabs != uses_tabs => {
+                        indentation.issues.push(IndentationIssue::Inconsistent { indentation: span, first, first_uses_tabs });
+                    },
+                    Some(_) => {},
+                }
+            }
+
+            let width = text.chars().count();
+            let mut current = *levels.last().unwrap_or(&0);
+            if width > current {
+                levels.push(width);
+                indentation.changes.push((index, TokenType::Indent, start_of(token)));
+            }
+            while width < current {
+                levels.pop();
+                indentation.changes.push((index, TokenType::Dedent, start_of(token)));
+                current = *levels.last().unwrap_or(&0);
+                if width > current {
+                    indentation.issues.push(IndentationIssue::UnmatchedDedent { indentation: span });
+                    //carry on as if this width opened a new block, so the next line isn't reported
+                    //too. It gets its own Indent so every Dedent still closes one
+                    levels.push(width);
+                    indentation.changes.push((index, TokenType::Indent, start_of(token)));
+                    break;
+                }
+            }
+        }
+        line_start = kind == TokenType::Newline;
+        leading = None;
+    }
+    return indentation;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn changes(source: &str) -> Vec<(usize, TokenType)> {
+        let (tokens, _) = Lexer::new("my_file".into()).with_recovery().lex_with_errors(source.into());
+        return check_indentation(&tokens).changes.iter().map(|(line, kind, _)| (*line, *kind)).collect();
+    }
+
+    fn issues(source: &str) -> Vec<IndentationIssue> {
+        let (tokens, _) = Lexer::new("my_file".into()).with_indentation().with_recovery().lex_with_errors(source.into());
+        return check_indentation(&tokens).issues;
+    }
+
+    #[test]
+    fn indent_and_dedent_at_end_of_file() {
+        //a, newline, whitespace, b, newline, end of file
+        assert_eq!(changes("a\n  b\n"), vec![(3, TokenType::Indent), (5, TokenType::Dedent)]);
+    }
+
+    #[test]
+    fn blank_and_comment_lines_ignored() {
+        assert_eq!(changes("a\n\n//note\n  b\n"), vec![(6, TokenType::Indent), (8, TokenType::Dedent)]);
+    }
+
+    #[test]
+    fn continued_lines_ignored() {
+        assert_eq!(changes("a = (1\n    + 2)\nb\n"), vec![]);
+    }
+
+    #[test]
+    fn dedents_several_levels() {
+        assert_eq!(changes("a\n b\n  c\nd\n"), vec![(3, TokenType::Indent), (6, TokenType::Indent),
+            (8, TokenType::Dedent), (8, TokenType::Dedent)]);
+    }
+
+    #[test]
+    fn unmatched_dedent() {
+        assert!(matches!(issues("a\n    b\n  c\n")[..], [IndentationIssue::UnmatchedDedent { .. }]));
+        let changes = changes("a\n    b\n  c\n  d\ne\n");
+        let count = |kind| changes.iter().filter(|(_, change)| *change == kind).count();
+        assert_eq!(count(TokenType::Indent), count(TokenType::Dedent));
+        assert_eq!(count(TokenType::Indent), 2);
+    }
+
+    #[test]
+    fn tabs_and_spaces() {
+        assert!(matches!(issues("a\n \tb\n")[..], [IndentationIssue::Mixed { .. }]));
+        assert!(matches!(issues("a\n  b\nc\n\td\n")[..], [IndentationIssue::Inconsistent { first_uses_tabs: false, .. }]));
+    }
+}
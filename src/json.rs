@@ -0,0 +1,135 @@
+//just enough JSON writing for the structured outputs, since serde isn't available
+use crate::diagnostic::Label;
+use crate::diff::DiffOp;
+use crate::lexer::{LexError, LiteralValue, Span, Token};
+
+pub fn string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            c if (c as u32) < 0x20 => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    return escaped;
+}
+
+pub fn array(items: &[String]) -> String {
+    return format!("[{}]", items.join(","));
+}
+
+//fields are written in the order given, so output is stable
+pub fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}:{}", string(key), value)).collect();
+    return format!("{{{}}}", fields.join(","));
+}
+
+pub fn span(span: Span) -> String {
+    return object(&[
+        ("start_line", span.start_line.to_string()),
+        ("end_line", span.end_line.to_string()),
+        ("start_index", span.start_index.to_string()),
+        ("end_index", span.end_index.to_string()),
+        ("start_byte", span.start_byte.to_string()),
+        ("end_byte", span.end_byte.to_string()),
+    ]);
+}
+
+pub fn literal_value(value: Option<&LiteralValue>) -> String {
+    match value {
+        Some(LiteralValue::Integer(integer)) => return integer.to_string(),
+        //the lexer rejects infinite floats, so this is always a valid JSON number
+        Some(LiteralValue::Float(float)) => return float.to_string(),
+        Some(LiteralValue::String(string_value)) => return string(string_value),
+        None => return "null".to_string(),
+    }
+}
+
+pub fn token(token: &Token) -> String {
+    return object(&[
+        ("kind", string(token.kind().name())),
+        ("text", string(token.text())),
+        ("span", span(token.span())),
+        ("value", literal_value(token.literal_value())),
+    ]);
+}
+
+pub fn lex_error(error: &LexError) -> String {
+    return object(&[
+        ("file", string(error.file())),
+        ("error_type", string(error.error_type().name())),
+        ("severity", string(if error.error_type().is_warning() { "warning" } else { "error" })),
+        ("message", string(&error.error_type().to_string())),
+        ("span", span(error.span())),
+        ("labels", array(&error.labels().iter().map(label).collect::<Vec<String>>())),
+    ]);
+}
+
+//only the changes, like the text diff, since unchanged tokens would be most of the output
+pub fn diff(ops: &[DiffOp]) -> String {
+    let changes: Vec<String> = ops.iter().filter_map(|op| match op {
+        DiffOp::Same(_, _) => None,
+        DiffOp::Removed(removed) => Some(object(&[("op", string("removed")), ("token", token(removed))])),
+        DiffOp::Added(added) => Some(object(&[("op", string("added")), ("token", token(added))])),
+    }).collect();
+    return array(&changes);
+}
+
+pub fn label(label: &Label) -> String {
+    return object(&[
+        ("span", span(label.span)),
+        ("message", string(&label.message)),
+    ]);
+}
+
+pub fn io_error(file: &str, error: &std::io::Error) -> String {
+    return object(&[
+        ("file", string(file)),
+        ("error_type", string("io")),
+        ("message", string(&error.to_string())),
+    ]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(string("say \"hi\"\n\\"), "\"say \\\"hi\\\"\\n\\\\\"");
+        assert_eq!(string("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn token_json() {
+        let tokens = Lexer::new("my_file".into()).lex("0x1f\n".into()).unwrap();
+        assert_eq!(token(&tokens[0]), "{\"kind\":\"hex_literal\",\"text\":\"0x1f\",\"span\":{\"start_line\":1,\"end_line\":1,\"start_index\":0,\"end_index\":4,\"start_byte\":0,\"end_byte\":4},\"value\":31}");
+    }
+
+    #[test]
+    fn lex_error_json() {
+        let error = Lexer::new("my_file".into()).lex("'\n".into()).unwrap_err();
+        assert_eq!(lex_error(&error), "{\"file\":\"my_file\",\"error_type\":\"wrong_quotes\",\"severity\":\"error\",\"message\":\"Wrong quotes\",\"span\":{\"start_line\":1,\"end_line\":1,\"start_index\":0,\"end_index\":1,\"start_byte\":0,\"end_byte\":1},\"labels\":[]}");
+    }
+
+    #[test]
+    fn diff_json() {
+        let old = Lexer::new("my_file".into()).lex("x\n".into()).unwrap();
+        let new = Lexer::new("my_file".into()).lex("y\n".into()).unwrap();
+        let ops = crate::diff::diff_tokens(&old, &new);
+        assert_eq!(diff(&ops), format!("[{{\"op\":\"removed\",\"token\":{}}},{{\"op\":\"added\",\"token\":{}}}]",
+            token(&old[0]), token(&new[0])));
+    }
+
+    #[test]
+    fn builds_objects() {
+        assert_eq!(object(&[("a", "1".into()), ("b", array(&[string("x")]))]), "{\"a\":1,\"b\":[\"x\"]}");
+    }
+}
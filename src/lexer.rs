@@ -1,11 +1,66 @@
+use crate::cancel::CancellationToken;
+use crate::delimiters::{self, Imbalance};
+use crate::diagnostic::Label;
+use crate::edition::LanguageVersion;
+use crate::features::UnstableFeature;
+use crate::indentation::IndentationIssue;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LiteralValue {
+    Integer(u64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start_line: usize,
+    pub end_line: usize,
+    //indexes are in characters from the start of the line, bytes are for tools that need offsets
+    pub start_index: usize,
+    pub end_index: usize,
+    pub start_byte: usize,
+    pub end_byte: usize,
+}
+
 #[derive(Debug)]
 pub struct Token {
     token_type: TokenType,
     value: String,
-    start_line: usize,
-    end_line: usize,
-    start_index: usize,
-    end_index: usize,
+    literal_value: Option<LiteralValue>,
+    span: Span,
+}
+impl Token {
+    pub fn kind(&self) -> TokenType {
+        return self.token_type;
+    }
+
+    pub fn text(&self) -> &str {
+        return &self.value;
+    }
+
+    pub fn span(&self) -> Span {
+        return self.span;
+    }
+
+    pub fn literal_value(&self) -> Option<&LiteralValue> {
+        return self.literal_value.as_ref();
+    }
+
+    pub fn is_literal(&self) -> bool {
+        return matches!(self.token_type, TokenType::BinLiteral | TokenType::HexLiteral |
+            TokenType::DecimalLiteral | TokenType::StringLiteral);
+    }
+
+    pub fn is_operator(&self) -> bool {
+        return matches!(self.token_type, TokenType::Operator(_));
+    }
+
+    //tokens that carry no meaning for the parser
+    pub fn is_trivia(&self) -> bool {
+        return matches!(self.token_type, TokenType::Whitespace | TokenType::LineComment |
+            TokenType::LineContinuation | TokenType::IgnoredNewline);
+    }
 }
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -17,50 +72,90 @@ impl std::fmt::Display for Token {
 pub struct LexError {
     error_type: LexErrorType,
     partial_token: String,
-    start_line: usize,
-    end_line: usize,
-    start_index: usize,
-    end_index: usize,
+    span: Span,
     file: String,
-    file_contents: String
+    file_contents: String,
+    labels: Vec<Label>,
 }
+impl LexError {
+    pub fn error_type(&self) -> LexErrorType {
+        return self.error_type;
+    }
+
+    pub fn span(&self) -> Span {
+        return self.span;
+    }
+
+    pub fn file(&self) -> &str {
+        return &self.file;
+    }
+
+    pub fn partial_token(&self) -> &str {
+        return &self.partial_token;
+    }
+}
+impl std::error::Error for LexError {}
 impl std::fmt::Display for LexError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Error while lexing file {}\n", self.file)?;
-
-        let index_num = if self.start_index == self.end_index {
-            self.start_index.to_string()
-        } else {
-            self.start_index.to_string() + "-" + &self.end_index.to_string()
-        };
+        //labels can be on other lines, which needs the gutters to be readable
+        if !self.labels.is_empty() {
+            return write!(f, "{}", self.render(0));
+        }
+        write!(f, "{}", self.header())?;
 
         let underline: String;
         let line: String;
-        let line_num = if self.start_line == self.end_line {
+        if self.span.start_line == self.span.end_line {
             //single line error:
-            line = self.file_contents.lines().nth(self.start_line - 1).unwrap().to_string();
-            underline = " ".repeat(self.start_index as usize) +
-                &"^".repeat(self.end_index - self.start_index) +
-                &"\n";
-            "line ".to_string() + &self.start_line.to_string() + ", index " + &index_num
+            line = self.file_contents.lines().nth(self.span.start_line - 1).unwrap_or_default().to_string();
+            underline = crate::diagnostic::underline(&line, &self.span) + "\n";
         } else {
             //multi-line error
             line = self.file_contents.lines()
-                .skip(self.start_line - 1)
-                .take(self.end_line - self.start_line + 1)
+                .skip(self.span.start_line - 1)
+                .take(self.span.end_line - self.span.start_line + 1)
                 .map(|x| x.to_owned()).collect::<Vec<String>>()
                 .join("\n");
             underline = "".into();
-            "lines ".to_string() + &self.start_line.to_string() + "-" + &self.end_line.to_string()
         };
 
-        write!(f, "{} on {}:\n", self.error_type.to_string(), line_num)?;
         write!(f, "{}\n{}", line, underline)
     }
 }
+impl LexError {
+    //like Display, but with line number gutters and `context` lines around the error
+    pub fn render(&self, context: usize) -> String {
+        return self.header() + &crate::diagnostic::render_snippet(&self.file_contents, &self.span, &self.labels, context);
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> LexError {
+        self.labels.push(Label { span, message: message.into() });
+        return self;
+    }
+
+    //other locations involved in the error, as opposed to the span where it was found
+    pub fn labels(&self) -> &[Label] {
+        return &self.labels;
+    }
 
-#[derive(Debug, PartialEq)]
-enum LexErrorType {
+    fn header(&self) -> String {
+        let index_num = if self.span.start_index == self.span.end_index {
+            self.span.start_index.to_string()
+        } else {
+            self.span.start_index.to_string() + "-" + &self.span.end_index.to_string()
+        };
+        let line_num = if self.span.start_line == self.span.end_line {
+            "line ".to_string() + &self.span.start_line.to_string() + ", index " + &index_num
+        } else {
+            "lines ".to_string() + &self.span.start_line.to_string() + "-" + &self.span.end_line.to_string()
+        };
+        let severity = if self.error_type.is_warning() { "Warning" } else { "Error" };
+        return format!("{} while lexing file {}\n{} on {}:\n", severity, self.file, self.error_type, line_num);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LexErrorType {
     WrongQuotes,
     MalformedBinLiteral,
     WrongHexCase,
@@ -74,6 +169,22 @@ enum LexErrorType {
     UnexpectedEOFString,
     MissingTrailingNewLine,
     TrailingWhitespace,
+    InvalidEscape,
+    IntegerOverflow,
+    FloatOverflow,
+    InternalError,
+    Cancelled,
+    UnclosedDelimiter,
+    MismatchedDelimiter,
+    UnexpectedClosingDelimiter,
+    MisplacedLineContinuation,
+    DanglingLineContinuation,
+    MixedIndentation,
+    InconsistentIndentation,
+    UnmatchedDedent,
+    UnknownEdition,
+    UnstableFeature,
+    UnknownFeature,
 }
 impl std::fmt::Display for LexErrorType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -91,16 +202,73 @@ impl std::fmt::Display for LexErrorType {
             LexErrorType::UnexpectedEOFString => write!(f, "Found EOF while looking for closing quotation marks"),
             LexErrorType::MissingTrailingNewLine => write!(f, "File doesn't end with a trailing newline"),
             LexErrorType::TrailingWhitespace => write!(f, "Trailing whitespace"),
+            LexErrorType::InvalidEscape => write!(f, "Invalid escape sequence in string literal"),
+            LexErrorType::IntegerOverflow => write!(f, "Integer literal too large to fit in 64 bits"),
+            LexErrorType::FloatOverflow => write!(f, "Decimal literal too large to represent"),
+            LexErrorType::InternalError => write!(f, "Internal compiler error (this is a bug, please report it)"),
+            LexErrorType::Cancelled => write!(f, "Lexing was cancelled"),
+            LexErrorType::UnclosedDelimiter => write!(f, "Unclosed delimiter at end of file"),
+            LexErrorType::MismatchedDelimiter => write!(f, "Mismatched closing delimiter"),
+            LexErrorType::UnexpectedClosingDelimiter => write!(f, "Closing delimiter with no matching opening delimiter"),
+            LexErrorType::MisplacedLineContinuation => write!(f, "Backslash that isn't at the end of a line"),
+            LexErrorType::DanglingLineContinuation => write!(f, "Line continuation with no line after it"),
+            LexErrorType::MixedIndentation => write!(f, "Indentation mixes tabs and spaces"),
+            LexErrorType::InconsistentIndentation => write!(f, "Indentation doesn't use the same character as the rest of the file"),
+            LexErrorType::UnmatchedDedent => write!(f, "Dedent doesn't match any outer indentation level"),
+            LexErrorType::UnknownEdition => write!(f, "Unknown language edition"),
+            LexErrorType::UnstableFeature => write!(f, "Unstable feature used without enabling it"),
+            LexErrorType::UnknownFeature => write!(f, "Unknown unstable feature"),
         }
     }
 }
+impl LexErrorType {
+    //stable identifier for tooling, like TokenType::name
+    pub fn name(&self) -> &'static str {
+        match self {
+            LexErrorType::WrongQuotes => "wrong_quotes",
+            LexErrorType::MalformedBinLiteral => "malformed_bin_literal",
+            LexErrorType::WrongHexCase => "wrong_hex_case",
+            LexErrorType::MalformedHexLiteral => "malformed_hex_literal",
+            LexErrorType::MalformedDecLiteral => "malformed_dec_literal",
+            LexErrorType::MultipleDecimalPoints => "multiple_decimal_points",
+            LexErrorType::UnexpectedCharacter => "unexpected_character",
+            LexErrorType::TrailingDPoint => "trailing_decimal_point",
+            LexErrorType::EmptyBinLiteral => "empty_bin_literal",
+            LexErrorType::EmptyHexLiteral => "empty_hex_literal",
+            LexErrorType::UnexpectedEOFString => "unexpected_eof_in_string",
+            LexErrorType::MissingTrailingNewLine => "missing_trailing_newline",
+            LexErrorType::TrailingWhitespace => "trailing_whitespace",
+            LexErrorType::InvalidEscape => "invalid_escape",
+            LexErrorType::IntegerOverflow => "integer_overflow",
+            LexErrorType::FloatOverflow => "float_overflow",
+            LexErrorType::InternalError => "internal_error",
+            LexErrorType::Cancelled => "cancelled",
+            LexErrorType::UnclosedDelimiter => "unclosed_delimiter",
+            LexErrorType::MismatchedDelimiter => "mismatched_delimiter",
+            LexErrorType::UnexpectedClosingDelimiter => "unexpected_closing_delimiter",
+            LexErrorType::MisplacedLineContinuation => "misplaced_line_continuation",
+            LexErrorType::DanglingLineContinuation => "dangling_line_continuation",
+            LexErrorType::MixedIndentation => "mixed_indentation",
+            LexErrorType::InconsistentIndentation => "inconsistent_indentation",
+            LexErrorType::UnmatchedDedent => "unmatched_dedent",
+            LexErrorType::UnknownEdition => "unknown_edition",
+            LexErrorType::UnstableFeature => "unstable_feature",
+            LexErrorType::UnknownFeature => "unknown_feature",
+        }
+    }
+
+    //the style checks don't change what the file means, so they're reported as warnings
+    pub fn is_warning(&self) -> bool {
+        return crate::directives::ALLOWABLE_ERRORS.contains(self);
+    }
+}
 
 #[derive(PartialEq, Debug, Clone, Copy)]
-enum TokenType {
+pub enum TokenType {
     BinLiteral,
     HexLiteral,
-    DecimalLiteral(bool), //has_decimal_point
-    StringLiteral(bool), //next_char_escaped
+    DecimalLiteral,
+    StringLiteral,
     Operator(Operator),
     LineComment,
 
@@ -108,6 +276,8 @@ enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
 
     Equals,
 
@@ -115,7 +285,16 @@ enum TokenType {
 
     Whitespace,
     Newline,
+    //a backslash at the end of a line, which carries the statement on to the next line
+    LineContinuation,
+    //a newline inside parentheses or brackets, which can't end a statement
+    IgnoredNewline,
+    //the start and end of a block in indentation mode, which don't cover any text
+    Indent,
+    Dedent,
     EndOfFile,
+
+    Error,
 }
 
 impl std::fmt::Display for TokenType {
@@ -123,8 +302,8 @@ impl std::fmt::Display for TokenType {
         match self {
             TokenType::BinLiteral => write!(f, "Binary literal"),
             TokenType::HexLiteral => write!(f, "Hexadecimal literal"),
-            TokenType::DecimalLiteral(_) => write!(f, "Decimal literal"),
-            TokenType::StringLiteral(_) => write!(f, "String literal"),
+            TokenType::DecimalLiteral => write!(f, "Decimal literal"),
+            TokenType::StringLiteral => write!(f, "String literal"),
             TokenType::Operator(Operator::Plus) => write!(f, "Plus operator"),
             TokenType::Operator(Operator::Minus) => write!(f, "Minus operator"),
             TokenType::Operator(Operator::Multiply) => write!(f, "Multiply operator"),
@@ -135,17 +314,58 @@ impl std::fmt::Display for TokenType {
             TokenType::RightParen => write!(f, "Right paren"),
             TokenType::LeftBrace => write!(f, "Left brace"),
             TokenType::RightBrace => write!(f, "Right brace"),
+            TokenType::LeftBracket => write!(f, "Left bracket"),
+            TokenType::RightBracket => write!(f, "Right bracket"),
             TokenType::Identifier => write!(f, "Identifier"),
             TokenType::Whitespace => write!(f, "Whitespace"),
             TokenType::Newline => write!(f, "Newline"),
+            TokenType::LineContinuation => write!(f, "Line continuation"),
+            TokenType::IgnoredNewline => write!(f, "Newline inside brackets"),
+            TokenType::Indent => write!(f, "Indent"),
+            TokenType::Dedent => write!(f, "Dedent"),
             TokenType::EndOfFile => write!(f, "End of file"),
             TokenType::Equals => write!(f, "Equals"),
+            TokenType::Error => write!(f, "Lexical error"),
+        }
+    }
+}
+
+impl TokenType {
+    //stable identifier for tooling, unlike the Display text which is meant for humans
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenType::BinLiteral => "bin_literal",
+            TokenType::HexLiteral => "hex_literal",
+            TokenType::DecimalLiteral => "decimal_literal",
+            TokenType::StringLiteral => "string_literal",
+            TokenType::Operator(Operator::Plus) => "plus",
+            TokenType::Operator(Operator::Minus) => "minus",
+            TokenType::Operator(Operator::Multiply) => "multiply",
+            TokenType::Operator(Operator::Divide) => "divide",
+            TokenType::Operator(Operator::Equals) => "equality",
+            TokenType::LineComment => "line_comment",
+            TokenType::LeftParen => "left_paren",
+            TokenType::RightParen => "right_paren",
+            TokenType::LeftBrace => "left_brace",
+            TokenType::RightBrace => "right_brace",
+            TokenType::LeftBracket => "left_bracket",
+            TokenType::RightBracket => "right_bracket",
+            TokenType::Identifier => "identifier",
+            TokenType::Whitespace => "whitespace",
+            TokenType::Newline => "newline",
+            TokenType::LineContinuation => "line_continuation",
+            TokenType::IgnoredNewline => "ignored_newline",
+            TokenType::Indent => "indent",
+            TokenType::Dedent => "dedent",
+            TokenType::EndOfFile => "end_of_file",
+            TokenType::Equals => "equals",
+            TokenType::Error => "error",
         }
     }
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
-enum Operator {
+pub enum Operator {
     Plus,
     Minus,
     Multiply,
@@ -158,22 +378,52 @@ pub struct Lexer {
     partial_token: String,
     current_char: Option<char>,
     proposed_token_type: Option<TokenType>,
+    //inside a string literal, whether the last character was a backslash starting an escape
+    next_char_escaped: bool,
 
     start_line: usize,
     end_line: usize,
     start_index: usize,
     end_index: usize,
+    start_byte: usize,
+    end_byte: usize,
 
     file: String,
-    file_contents: Option<String>
+    file_contents: Option<String>,
+    cancellation: Option<CancellationToken>,
+    recovery: bool,
+    indentation: bool,
+    version: LanguageVersion,
+    enabled_features: Vec<UnstableFeature>,
+    error_consumed_char: bool,
+    allowed: Vec<LexErrorType>,
+    //delimiters open at the current position, to decide whether a newline can end a statement
+    open_delimiters: Vec<TokenType>,
 }
 
 fn is_literal_terminator(current_char: char) -> bool {
-    if "+-*/!\"%^&(){}[].,|:; \n".contains(current_char) {
-        return true;
-    } else {
-        return false;
+    return "+-*/!\"%^&(){}[].,|:; \n\\".contains(current_char);
+}
+
+//strips the surrounding quotes and resolves escapes, which were already validated while lexing
+fn unescape_string(literal: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some('r') => unescaped.push('\r'),
+                Some('0') => unescaped.push('\0'),
+                Some(other) => unescaped.push(other),
+                None => {}
+            }
+        } else {
+            unescaped.push(c);
+        }
     }
+    return unescaped;
 }
 
 impl Lexer {
@@ -183,52 +433,327 @@ impl Lexer {
             partial_token: String::new(),
             current_char: None,
             proposed_token_type: None,
+            next_char_escaped: false,
 
             start_line: 1,
             end_line: 1,
             start_index: 0,
             end_index: 0,
+            start_byte: 0,
+            end_byte: 0,
 
             file: current_file,
             file_contents: None,
+            cancellation: None,
+            recovery: false,
+            indentation: false,
+            version: LanguageVersion::LATEST,
+            enabled_features: Vec::new(),
+            error_consumed_char: false,
+            allowed: Vec::new(),
+            open_delimiters: Vec::new(),
         }
     }
 
-    pub fn lex(mut self, source: String) -> Result<Vec<Token>,LexError> {
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Lexer {
+        self.cancellation = Some(cancellation);
+        return self;
+    }
+
+    pub fn lex(self, source: String) -> Result<Vec<Token>,LexError> {
+        let (tokens, mut errors) = self.lex_with_errors(source);
+        if errors.is_empty() {
+            return Ok(tokens);
+        } else {
+            return Err(errors.remove(0));
+        }
+    }
+
+    //in recovery mode, lexing continues after an error and every error is returned,
+    //with an Error token covering the bad text so the parser can build a tree around it
+    pub fn with_recovery(mut self) -> Lexer {
+        self.recovery = true;
+        return self;
+    }
+
+    //the offside rule, where leading whitespace opens and closes blocks with Indent and Dedent
+    //tokens. Tabs are allowed in whitespace, as long as the file doesn't mix them with spaces
+    pub fn with_indentation(mut self) -> Lexer {
+        self.indentation = true;
+        return self;
+    }
+
+    //lets files turn on the feature with a feature directive, like --unstable-feature
+    pub fn enable_feature(mut self, feature: UnstableFeature) -> Lexer {
+        self.enabled_features.push(feature);
+        return self;
+    }
+
+    //the language version to lex with, unless the file pins its own with an edition directive
+    pub fn with_version(mut self, version: LanguageVersion) -> Lexer {
+        self.version = version;
+        return self;
+    }
+
+    //skip one of the style checks in directives::ALLOWABLE_ERRORS, in addition to any the file
+    //allows itself
+    pub fn allow(mut self, error_type: LexErrorType) -> Lexer {
+        self.allowed.push(error_type);
+        return self;
+    }
+
+    fn is_allowed(&self, error_type: LexErrorType) -> bool {
+        return self.allowed.contains(&error_type);
+    }
+
+    pub fn lex_with_errors(mut self, source: String) -> (Vec<Token>, Vec<LexError>) {
+        let _phase = crate::log::enter_phase("lex");
+        crate::debug!("lexing {} ({} bytes)", self.file, source.len());
+        self.allowed.extend(crate::directives::allowed_errors(&source));
         self.file_contents = Some(source.clone());
+        let mut errors = Vec::new();
+        match crate::directives::edition(&source) {
+            Ok(Some(version)) => self.version = version,
+            Ok(None) => {},
+            Err(line_num) => {
+                let line_length = source.lines().nth(line_num - 1).unwrap_or_default().chars().count();
+                let line_bytes = source.lines().nth(line_num - 1).unwrap_or_default().len();
+                errors.push(self.construct_error_at(LexErrorType::UnknownEdition, Span { start_line: line_num,
+                    end_line: line_num, start_index: 0, end_index: line_length, start_byte: 0, end_byte: line_bytes }));
+                if !self.recovery {
+                    return (self.full_tokens, errors);
+                }
+            }
+        }
+        crate::debug!("using language version {}", self.version);
+        for (line_num, name, feature) in crate::directives::features(&source) {
+            let error = match feature {
+                Some(feature) if self.enabled_features.contains(&feature) => {
+                    match feature {
+                        UnstableFeature::Indentation => self.indentation = true,
+                    }
+                    continue;
+                },
+                Some(feature) => self.directive_error(LexErrorType::UnstableFeature, line_num, name,
+                    format!("enable it with --unstable-feature {}", feature)),
+                None => self.directive_error(LexErrorType::UnknownFeature, line_num, name,
+                    "not one of the unstable features this compiler has".into()),
+            };
+            errors.push(error);
+            if !self.recovery {
+                return (self.full_tokens, errors);
+            }
+        }
         for current_char in source.chars() {
-            match self.consume_char(current_char) {
-                Ok(()) => {},
-                Err(lex_error) => {
-                    return Err(lex_error);
+            if self.cancellation.as_ref().is_some_and(|token| token.is_cancelled()) {
+                errors.push(self.construct_error(LexErrorType::Cancelled));
+                return (self.full_tokens, errors);
+            }
+            self.error_consumed_char = false;
+            let mut result = self.consume_char(current_char);
+            //recovering can hit another error when the character is lexed again, but then the
+            //lexer is between tokens and every error there consumes the character
+            while let Err(lex_error) = result {
+                crate::debug!("{} on line {}", lex_error.error_type, lex_error.span.start_line);
+                let error_type = lex_error.error_type;
+                errors.push(lex_error);
+                if !self.recovery {
+                    return (self.full_tokens, errors);
                 }
+                result = self.recover(error_type, current_char);
             }
         }
 
         //partial token followed by EOF
         match self.proposed_token_type {
-            Some(TokenType::StringLiteral(_)) => {
-                return Err(self.construct_error(LexErrorType::UnexpectedEOFString))
+            Some(TokenType::StringLiteral) => {
+                errors.push(self.construct_error(LexErrorType::UnexpectedEOFString));
+                if !self.recovery {
+                    return (self.full_tokens, errors);
+                }
+                self.push_error_token();
+            },
+            //the last token ended by itself, like a closing bracket, operator or string
+            None => {
+                //a continuation ends with its newline, so it's only pushed once there's nothing to continue
+                let continuation = self.full_tokens.last().filter(|token| token.kind() == TokenType::LineContinuation);
+                if let Some(span) = continuation.map(|token| token.span()) {
+                    let backslash = Span { end_line: span.start_line, end_index: span.start_index + 1,
+                        end_byte: span.start_byte + 1, ..span };
+                    errors.push(self.construct_error_at(LexErrorType::DanglingLineContinuation, backslash));
+                    if !self.recovery {
+                        return (self.full_tokens, errors);
+                    }
+                }
+                if !source.is_empty() && !source.ends_with('\n') && !self.is_allowed(LexErrorType::MissingTrailingNewLine) {
+                    errors.push(self.construct_error(LexErrorType::MissingTrailingNewLine));
+                    if !self.recovery {
+                        return (self.full_tokens, errors);
+                    }
+                }
             },
-            None => {},
             Some(_) => {
-                return Err(self.construct_error(LexErrorType::MissingTrailingNewLine))
+                let unfinished = self.unfinished_token();
+                if let Some(error_type) = unfinished {
+                    errors.push(self.construct_error(error_type));
+                    if !self.recovery {
+                        return (self.full_tokens, errors);
+                    }
+                }
+                if !self.is_allowed(LexErrorType::MissingTrailingNewLine) {
+                    errors.push(self.construct_error(LexErrorType::MissingTrailingNewLine));
+                    if !self.recovery {
+                        return (self.full_tokens, errors);
+                    }
+                }
+                if unfinished.is_some() {
+                    self.push_error_token();
+                } else if let Err(lex_error) = self.push_token() {
+                    errors.push(lex_error);
+                    self.push_error_token();
+                }
             }
         }
         self.proposed_token_type = Some(TokenType::EndOfFile);
-        self.push_token();
+        if let Err(lex_error) = self.push_token() {
+            errors.push(lex_error);
+        }
+
+        if self.indentation {
+            let indentation = crate::indentation::check_indentation(&self.full_tokens);
+            for issue in indentation.issues {
+                errors.push(self.indentation_error(issue));
+                if !self.recovery {
+                    return (self.full_tokens, errors);
+                }
+            }
+            //inserted from the end so the earlier indexes stay right
+            for (index, token_type, span) in indentation.changes.into_iter().rev() {
+                self.full_tokens.insert(index, Token { token_type, value: String::new(), literal_value: None, span });
+            }
+        }
+
+        for imbalance in crate::delimiters::check_delimiters(&self.full_tokens) {
+            errors.push(self.delimiter_error(imbalance));
+            if !self.recovery {
+                break;
+            }
+        }
 
-        return Ok(self.full_tokens)
+        crate::debug!("produced {} tokens and {} errors", self.full_tokens.len(), errors.len());
+        return (self.full_tokens, errors)
+    }
+
+    //the checks the next character would make, for a token that the end of the file terminates
+    fn unfinished_token(&self) -> Option<LexErrorType> {
+        match self.proposed_token_type {
+            Some(TokenType::BinLiteral) if self.partial_token.ends_with('b') => return Some(LexErrorType::EmptyBinLiteral),
+            Some(TokenType::HexLiteral) if self.partial_token.ends_with('x') => return Some(LexErrorType::EmptyHexLiteral),
+            Some(TokenType::DecimalLiteral) if self.partial_token.ends_with('.') => return Some(LexErrorType::TrailingDPoint),
+            Some(TokenType::LineContinuation) => return Some(LexErrorType::DanglingLineContinuation),
+            _ => return None,
+        }
     }
 
-    fn push_token(&mut self) {
+    //gets the lexer back into a state where it can carry on after an error at current_char
+    fn recover(&mut self, error_type: LexErrorType, current_char: char) -> Result<(), LexError> {
+        if error_type == LexErrorType::InvalidEscape {
+            //the rest of the string is probably fine, so carry on lexing it
+            self.proposed_token_type = Some(TokenType::StringLiteral);
+            self.next_char_escaped = false;
+            return Ok(());
+        }
+        if !self.error_consumed_char {
+            self.push_error_token();
+            self.error_consumed_char = false;
+            return self.consume_char(current_char);
+        }
+        if current_char == '\n' {
+            //put the newline back so the line numbers stay right, and keep it as its own token
+            self.partial_token.pop();
+            self.end_index -= 1;
+            self.end_byte -= 1;
+            self.push_error_token();
+            self.push_char(current_char);
+            self.proposed_token_type = Some(self.newline_type());
+            return self.push_token();
+        }
+        self.push_error_token();
+        return Ok(());
+    }
+
+    //statements can't end inside parentheses or brackets, but braces hold statements
+    fn newline_type(&self) -> TokenType {
+        match self.open_delimiters.last() {
+            Some(TokenType::LeftParen) | Some(TokenType::LeftBracket) => return TokenType::IgnoredNewline,
+            _ => return TokenType::Newline,
+        }
+    }
+
+    fn push_error_token(&mut self) {
+        if self.partial_token.is_empty() {
+            self.proposed_token_type = None;
+            return;
+        }
+        self.proposed_token_type = Some(TokenType::Error);
+        //Error tokens have no literal value, so this can't fail
+        let _ = self.push_token();
+    }
+
+    fn push_token(&mut self) -> Result<(), LexError> {
+        let literal_value = self.literal_value()?;
+        let token_type = match std::mem::take(&mut self.proposed_token_type) {
+            Some(token_type) => token_type,
+            //push called before the token type was decided
+            None => return Err(self.construct_error(LexErrorType::InternalError)),
+        };
         self.full_tokens.push(Token {
-            token_type: std::mem::take(&mut self.proposed_token_type).expect("push called before token was type was decided"),
+            token_type,
             value: std::mem::take(&mut self.partial_token),
-            start_line: self.start_line, end_line: self.end_line, start_index: self.start_index, end_index: self.end_index });
+            literal_value,
+            span: self.current_span() });
+        if delimiters::closing(token_type).is_some() {
+            self.open_delimiters.push(token_type);
+        } else if delimiters::is_closing(token_type) {
+            //matches how check_delimiters recovers, so a mismatch doesn't leave a bracket open
+            if let Some(matching) = self.open_delimiters.iter().rposition(|open| delimiters::closing(*open) == Some(token_type)) {
+                self.open_delimiters.truncate(matching);
+            }
+        }
+        if let Some(token) = self.full_tokens.last() {
+            crate::trace!("pushed {} at {}:{}", token, self.start_line, self.start_index);
+        }
         self.start_line = self.end_line;
         self.start_index = self.end_index;
+        self.start_byte = self.end_byte;
         self.proposed_token_type = None;
+        self.next_char_escaped = false;
+        return Ok(())
+    }
+
+    //computes the value of the partial token if it is a literal, so later stages don't re-parse the text
+    fn literal_value(&self) -> Result<Option<LiteralValue>, LexError> {
+        let integer = match self.proposed_token_type {
+            Some(TokenType::BinLiteral) => u64::from_str_radix(&self.partial_token[2..], 2),
+            Some(TokenType::HexLiteral) => u64::from_str_radix(&self.partial_token[2..], 16),
+            Some(TokenType::DecimalLiteral) if !self.partial_token.contains('.') => self.partial_token.parse::<u64>(),
+            Some(TokenType::DecimalLiteral) => {
+                return match self.partial_token.parse::<f64>() {
+                    Ok(float) if float.is_finite() => Ok(Some(LiteralValue::Float(float))),
+                    _ => Err(self.construct_error(LexErrorType::FloatOverflow)),
+                }
+            },
+            Some(TokenType::StringLiteral) => {
+                return Ok(Some(LiteralValue::String(unescape_string(&self.partial_token))))
+            },
+            _ => return Ok(None),
+        };
+        //the digits have already been checked, at a terminator or by unfinished_token at the end
+        //of the file, so the only possible failure is overflow
+        match integer {
+            Ok(integer) => return Ok(Some(LiteralValue::Integer(integer))),
+            Err(_) => return Err(self.construct_error(LexErrorType::IntegerOverflow)),
+        }
     }
 
     fn push_char(&mut self, c: char) {
@@ -236,21 +761,87 @@ impl Lexer {
         if c == '\n' {
             self.end_line += 1;
             self.end_index = 0;
+            self.end_byte = 0;
         } else {
             self.end_index += 1;
+            self.end_byte += c.len_utf8();
         }
     }
 
+    fn current_span(&self) -> Span {
+        return Span { start_line: self.start_line, end_line: self.end_line,
+            start_index: self.start_index, end_index: self.end_index,
+            start_byte: self.start_byte, end_byte: self.end_byte };
+    }
+
     fn construct_error(&self, e_type: LexErrorType) -> LexError {
         let token = self.partial_token.clone();
         return LexError { error_type: e_type, partial_token: token,
-            start_line: self.start_line, end_line: self.end_line,
-            start_index: self.start_index, end_index: self.end_index,
-            file: self.file.clone(), file_contents: self.file_contents.clone().unwrap()}
+            span: self.current_span(),
+            file: self.file.clone(), file_contents: self.file_contents.clone().unwrap_or_default(), labels: Vec::new() }
+    }
+
+    fn delimiter_error(&self, imbalance: Imbalance) -> LexError {
+        let (error_type, span, label) = match imbalance {
+            Imbalance::Unclosed { kind, open, end_of_file } =>
+                (LexErrorType::UnclosedDelimiter, end_of_file, Some((open, format!("unclosed {} opened here", delimiters::name(kind))))),
+            Imbalance::Mismatched { kind, open, close } =>
+                (LexErrorType::MismatchedDelimiter, close, Some((open, format!("{} opened here", delimiters::name(kind))))),
+            Imbalance::Unexpected { close } => (LexErrorType::UnexpectedClosingDelimiter, close, None),
+        };
+        let error = self.construct_error_at(error_type, span);
+        match label {
+            Some((open, message)) => return error.with_label(open, message),
+            None => return error,
+        }
+    }
+
+    fn indentation_error(&self, issue: IndentationIssue) -> LexError {
+        match issue {
+            IndentationIssue::Mixed { indentation } => {
+                return self.construct_error_at(LexErrorType::MixedIndentation, indentation);
+            },
+            IndentationIssue::Inconsistent { indentation, first, first_uses_tabs } => {
+                let style = if first_uses_tabs { "tabs" } else { "spaces" };
+                return self.construct_error_at(LexErrorType::InconsistentIndentation, indentation)
+                    .with_label(first, format!("first indented with {} here", style));
+            },
+            IndentationIssue::UnmatchedDedent { indentation } => {
+                return self.construct_error_at(LexErrorType::UnmatchedDedent, indentation);
+            },
+        }
+    }
+
+    //an error covering a directive's line, labelling the part of it that is wrong
+    fn directive_error(&self, error_type: LexErrorType, line_num: usize, argument: &str, message: String) -> LexError {
+        let line = self.file_contents.as_deref().unwrap_or_default().lines().nth(line_num - 1).unwrap_or_default();
+        let line_span = Span { start_line: line_num, end_line: line_num, start_index: 0, end_index: line.chars().count(),
+            start_byte: 0, end_byte: line.len() };
+        let error = self.construct_error_at(error_type, line_span);
+        match line.find(argument) {
+            Some(start_byte) => {
+                let start_index = line[..start_byte].chars().count();
+                let argument_span = Span { start_index, end_index: start_index + argument.chars().count(),
+                    start_byte, end_byte: start_byte + argument.len(), ..line_span };
+                return error.with_label(argument_span, message);
+            },
+            None => return error,
+        }
+    }
+
+    //an error for a span found after lexing, rather than at the current position
+    fn construct_error_at(&self, error_type: LexErrorType, span: Span) -> LexError {
+        let file_contents = self.file_contents.clone().unwrap_or_default();
+        let text = file_contents.lines().nth(span.start_line - 1).unwrap_or_default()
+            .chars().skip(span.start_index).take(span.end_index - span.start_index).collect();
+        return LexError { error_type, partial_token: text, span,
+            file: self.file.clone(), file_contents, labels: Vec::new() };
     }
 
     fn construct_error_w_char(&mut self, e_type: LexErrorType) -> LexError {
+        self.error_consumed_char = true;
         self.end_index += 1;
+        self.end_byte += self.current_char.map(char::len_utf8).unwrap_or_default();
         self.partial_token.push(self.current_char.unwrap_or_default());
         return self.construct_error(e_type);
     }
@@ -263,14 +854,17 @@ impl Lexer {
                     self.push_char(current_char);
                     Ok(())
                 } else if is_literal_terminator(current_char) { //TODO: What if the literal is followed by an operator
-                    match self.partial_token.chars().last().unwrap() {
-                        'b' => {
-                            return Err(self.construct_error_w_char(LexErrorType::EmptyBinLiteral))
+                    match self.partial_token.chars().last() {
+                        Some('b') => {
+                            return Err(self.construct_error(LexErrorType::EmptyBinLiteral))
                         },
-                        _ => {
-                            self.push_token();
+                        Some(_) => {
+                            self.push_token()?;
                             return self.consume_char(current_char);
                         }
+                        None => {
+                            return Err(self.construct_error(LexErrorType::InternalError))
+                        }
                     }
                 } else {
                     return Err(self.construct_error_w_char(LexErrorType::MalformedBinLiteral))
@@ -283,17 +877,17 @@ impl Lexer {
                 } else if "ABCDEF".contains(current_char) {
                     return Err(self.construct_error_w_char(LexErrorType::WrongHexCase))
                 } else if is_literal_terminator(current_char) {
-                    if self.partial_token.chars().last().unwrap() == 'x' {
+                    if self.partial_token.ends_with('x') {
                         return Err(self.construct_error(LexErrorType::EmptyHexLiteral));
                     } else {
-                        self.push_token();
+                        self.push_token()?;
                         return self.consume_char(current_char);
                     }
                 } else {
                     return Err(self.construct_error_w_char(LexErrorType::MalformedHexLiteral))
                 }
             },
-            Some(TokenType::DecimalLiteral(has_decimal_point)) => {
+            Some(TokenType::DecimalLiteral) => {
                 if self.partial_token == "0" {
                     if current_char == 'b' {
                         self.proposed_token_type = Some(TokenType::BinLiteral);
@@ -310,31 +904,55 @@ impl Lexer {
                     self.push_char(current_char);
                     Ok(())
                 } else if current_char == '.' {
-                    if *has_decimal_point {
+                    if self.partial_token.contains('.') {
                             return Err(self.construct_error_w_char(LexErrorType::MultipleDecimalPoints))
                     } else {
-                        self.proposed_token_type = Some(TokenType::DecimalLiteral(true));
                         self.push_char(current_char);
                         Ok(())
                     }
                 } else if is_literal_terminator(current_char) {
-                    match self.partial_token.chars().last().unwrap() {
-                        '.' => {
+                    match self.partial_token.chars().last() {
+                        Some('.') => {
                             return Err(self.construct_error_w_char(LexErrorType::TrailingDPoint))
                         },
-                        _ => {
-                            self.push_token();
+                        Some(_) => {
+                            self.push_token()?;
                             return self.consume_char(current_char);
                         }
+                        None => {
+                            return Err(self.construct_error(LexErrorType::InternalError))
+                        }
                     }
                 } else {
                     return Err(self.construct_error_w_char(LexErrorType::MalformedDecLiteral))
                 }
             },
-            Some(TokenType::StringLiteral(escaped)) => {
-                if (current_char == '"') && (! escaped) {
+            Some(TokenType::StringLiteral) => {
+                if self.next_char_escaped {
+                    if !"ntr0\\\"".contains(current_char) {
+                        //the string so far can be long or go over several lines, so point at
+                        //just the backslash and the character after it
+                        let escape = Span { start_line: self.end_line, end_line: self.end_line,
+                            start_index: self.end_index - 1, end_index: self.end_index + 1,
+                            start_byte: self.end_byte - 1, end_byte: self.end_byte + current_char.len_utf8() };
+                        //through push_char rather than construct_error_w_char, so a backslash at the
+                        //end of a line still moves the position on to the next line
+                        self.error_consumed_char = true;
+                        self.push_char(current_char);
+                        let mut lex_error = self.construct_error(LexErrorType::InvalidEscape);
+                        lex_error.span = escape;
+                        return Err(lex_error)
+                    }
+                    self.next_char_escaped = false;
+                    self.push_char(current_char);
+                    return Ok(())
+                } else if current_char == '"' {
+                    self.push_char(current_char);
+                    self.push_token()?;
+                    return Ok(())
+                } else if current_char == '\\' {
+                    self.next_char_escaped = true;
                     self.push_char(current_char);
-                    self.push_token();
                     return Ok(())
                 } else {
                     self.push_char(current_char);
@@ -343,14 +961,17 @@ impl Lexer {
             },
             Some(TokenType::LineComment) => {
                 if current_char == '\n' {
-                    match self.partial_token.chars().last().unwrap() {
-                        ' ' => {
+                    match self.partial_token.chars().last() {
+                        Some(' ') if !self.is_allowed(LexErrorType::TrailingWhitespace) => {
                             return Err(self.construct_error(LexErrorType::TrailingWhitespace));
                         },
-                        _ => {
-                            self.push_token();
+                        Some(_) => {
+                            self.push_token()?;
                             return self.consume_char(current_char);
                         }
+                        None => {
+                            return Err(self.construct_error(LexErrorType::InternalError))
+                        }
                     }
                 } else {
                     self.push_char(current_char);
@@ -365,21 +986,30 @@ impl Lexer {
                             self.push_char(current_char);
                             return Ok(())
                         } else {
-                            self.push_token();
+                            self.push_token()?;
                             return self.consume_char(current_char);
                         }
                     },
                     _ => {
-                        panic!("unterminated operator token")
+                        return Err(self.construct_error(LexErrorType::InternalError))
                     }
                 }
             },
+            Some(TokenType::LineContinuation) => {
+                if current_char == '\n' {
+                    self.push_char(current_char);
+                    self.push_token()?;
+                    return Ok(())
+                } else {
+                    return Err(self.construct_error(LexErrorType::MisplacedLineContinuation))
+                }
+            },
             Some(TokenType::Whitespace) => {
-                if current_char == ' ' {
+                if current_char == ' ' || (current_char == '\t' && self.indentation) {
                     self.push_char(current_char);
                     Ok(())
                 } else {
-                    self.push_token();
+                    self.push_token()?;
                     return self.consume_char(current_char);
                 }
             },
@@ -388,7 +1018,7 @@ impl Lexer {
                     self.push_char(current_char);
                     return Ok(());
                 } else {
-                    self.push_token();
+                    self.push_token()?;
                     return self.consume_char(current_char);
 
                 }
@@ -397,28 +1027,32 @@ impl Lexer {
                 if current_char == '=' {
                     self.proposed_token_type = Some(TokenType::Operator(Operator::Equals));
                     self.push_char(current_char);
-                    self.push_token();
+                    self.push_token()?;
                     Ok(())
                 } else {
-                    self.push_token();
+                    self.push_token()?;
                     return self.consume_char(current_char);
                 }
             }
             Some(TokenType::LeftBrace) | Some(TokenType::RightBrace) |
             Some(TokenType::LeftParen) | Some(TokenType::RightParen) |
-            Some(TokenType::Newline) | Some(TokenType::EndOfFile) => {
-                panic!("Unexpected partial token")
+            Some(TokenType::LeftBracket) | Some(TokenType::RightBracket) |
+            Some(TokenType::IgnoredNewline) | Some(TokenType::Newline) |
+            Some(TokenType::Indent) | Some(TokenType::Dedent) | Some(TokenType::EndOfFile) |
+            Some(TokenType::Error) => {
+                //these tokens are always pushed as soon as they are started
+                return Err(self.construct_error(LexErrorType::InternalError))
             }
             None => {
                 match current_char {
                     '0'..='9' => {
                         self.push_char(current_char);
-                        self.proposed_token_type = Some(TokenType::DecimalLiteral(false));
+                        self.proposed_token_type = Some(TokenType::DecimalLiteral);
                         return Ok(())
                     },
                     '"' => {
                         self.push_char(current_char);
-                        self.proposed_token_type = Some(TokenType::StringLiteral(false));
+                        self.proposed_token_type = Some(TokenType::StringLiteral);
                         return Ok(())
                     },
                     '\'' => {
@@ -427,19 +1061,19 @@ impl Lexer {
                     '+' => {
                         self.push_char(current_char);
                         self.proposed_token_type = Some(TokenType::Operator(Operator::Plus));
-                        self.push_token();
+                        self.push_token()?;
                         return Ok(())
                     },
                     '-' => {
                         self.push_char(current_char);
                         self.proposed_token_type = Some(TokenType::Operator(Operator::Minus));
-                        self.push_token();
+                        self.push_token()?;
                         return Ok(())
                     },
                     '*' => {
                         self.push_char(current_char);
                         self.proposed_token_type = Some(TokenType::Operator(Operator::Multiply));
-                        self.push_token();
+                        self.push_token()?;
                         return Ok(())
                     },
                     '/' => {
@@ -451,25 +1085,37 @@ impl Lexer {
                     '(' => {
                         self.push_char(current_char);
                         self.proposed_token_type = Some(TokenType::LeftParen);
-                        self.push_token();
+                        self.push_token()?;
                         return Ok(())
                     },
                     ')' => {
                         self.push_char(current_char);
                         self.proposed_token_type = Some(TokenType::RightParen);
-                        self.push_token();
+                        self.push_token()?;
                         return Ok(())
                     },
                     '{' => {
                         self.push_char(current_char);
                         self.proposed_token_type = Some(TokenType::LeftBrace);
-                        self.push_token();
+                        self.push_token()?;
                         return Ok(())
                     },
                     '}' => {
                         self.push_char(current_char);
                         self.proposed_token_type = Some(TokenType::RightBrace);
-                        self.push_token();
+                        self.push_token()?;
+                        return Ok(())
+                    },
+                    '[' => {
+                        self.push_char(current_char);
+                        self.proposed_token_type = Some(TokenType::LeftBracket);
+                        self.push_token()?;
+                        return Ok(())
+                    },
+                    ']' => {
+                        self.push_char(current_char);
+                        self.proposed_token_type = Some(TokenType::RightBracket);
+                        self.push_token()?;
                         return Ok(())
                     },
 
@@ -483,21 +1129,25 @@ impl Lexer {
                         self.proposed_token_type = Some(TokenType::Whitespace);
                         return Ok(())
                     },
+                    '\t' if self.indentation => {
+                        self.push_char(current_char);
+                        self.proposed_token_type = Some(TokenType::Whitespace);
+                        return Ok(())
+                    },
                     '\n' => {
-                        match self.full_tokens.last() {
-                            Some(token) => {
-                                match token.token_type {
-                                    TokenType::Whitespace => {
-                                        return Err(self.construct_error_w_char(LexErrorType::TrailingWhitespace));
-                                    },
-                                    _ => {}
-                                }
-                            },
-                            None => {}
+                        if let Some(token) = self.full_tokens.last() {
+                            if token.token_type == TokenType::Whitespace && !self.is_allowed(LexErrorType::TrailingWhitespace) {
+                                return Err(self.construct_error_w_char(LexErrorType::TrailingWhitespace));
+                            }
                         }
                         self.push_char(current_char);
-                        self.proposed_token_type = Some(TokenType::Newline);
-                        self.push_token();
+                        self.proposed_token_type = Some(self.newline_type());
+                        self.push_token()?;
+                        return Ok(())
+                    },
+                    '\\' => {
+                        self.push_char(current_char);
+                        self.proposed_token_type = Some(TokenType::LineContinuation);
                         return Ok(())
                     },
                     '=' => {
@@ -531,11 +1181,23 @@ mod tests {
                 panic!("Error not thrown when expected");
             },
             Err(e) => {
-                return e.error_type;
+                return e.error_type();
             }
         }
     }
 
+    fn lex_to_values(source: &str) -> Vec<LiteralValue> {
+        let lexer = Lexer::new("my_file".into());
+        let tokens = lexer.lex(source.into()).expect("Unexpected error during test");
+        return tokens.iter().filter_map(|x| x.literal_value().cloned()).collect();
+    }
+
+    fn lex_recovering(source: &str) -> (Vec<TokenType>, Vec<LexErrorType>) {
+        let lexer = Lexer::new("my_file".into()).with_recovery();
+        let (tokens, errors) = lexer.lex_with_errors(source.into());
+        return (tokens.iter().map(|x| x.token_type).collect(), errors.iter().map(|x| x.error_type).collect());
+    }
+
     fn lex(source: &str) -> Result<Vec<Token>, LexError>{
         let lexer = Lexer::new("my_file".into());
         return lexer.lex(source.into())
@@ -547,6 +1209,41 @@ mod tests {
             vec![TokenType::Identifier, TokenType::Newline, TokenType::EndOfFile]);
     }
 
+    #[test]
+    fn integer_values() {
+        assert_eq!(lex_to_values("0b101 0xff 42\n"),
+            vec![LiteralValue::Integer(5), LiteralValue::Integer(255), LiteralValue::Integer(42)]);
+    }
+
+    #[test]
+    fn float_value() {
+        assert_eq!(lex_to_values("56.25\n"), vec![LiteralValue::Float(56.25)]);
+    }
+
+    #[test]
+    fn string_value_unescaped() {
+        assert_eq!(lex_to_values("\"say \\\"hi\\\"\\n\"\n"),
+            vec![LiteralValue::String("say \"hi\"\n".into())]);
+    }
+
+    #[test]
+    fn token_accessors() {
+        let tokens = lex("x = 0b1 // note\n").expect("Unexpected error during test");
+        assert_eq!(tokens[0].text(), "x");
+        assert_eq!(tokens[0].kind().name(), "identifier");
+        assert!(tokens[1].is_trivia());
+        assert!(tokens[4].is_literal());
+        assert_eq!(tokens[4].span(), Span { start_line: 1, end_line: 1, start_index: 4, end_index: 7, start_byte: 4, end_byte: 7 });
+        assert!(tokens[6].is_trivia());
+        assert!(!tokens[6].is_operator());
+    }
+
+    #[test]
+    fn operator_predicate() {
+        let tokens = lex("1+2\n").expect("Unexpected error during test");
+        assert!(tokens[1].is_operator());
+        assert_eq!(tokens[1].kind().name(), "plus");
+    }
 
     // Test the various errors
     #[test]
@@ -561,7 +1258,7 @@ mod tests {
 
     #[test]
     fn wrong_quote_inside() {
-        match lex("\"don't want an error here\"") {
+        match lex("\"don't want an error here\"\n") {
             Ok(_) => {},
             Err(e) => {
                 println!("{}", e);
@@ -662,6 +1359,224 @@ mod tests {
         assert_eq!(lex_to_err("//This is a comment with trailing whitespace \nlet x = 5\n"), LexErrorType::TrailingWhitespace);
     }
 
+    #[test]
+    fn invalid_escape() {
+        assert_eq!(lex_to_err("\"bad \\q escape\"\n"), LexErrorType::InvalidEscape);
+    }
+
+    #[test]
+    fn newlines_inside_brackets() {
+        assert_eq!(lex_to_tokens("(a\n)\n{\n}\n"), vec![TokenType::LeftParen, TokenType::Identifier,
+            TokenType::IgnoredNewline, TokenType::RightParen, TokenType::Newline, TokenType::LeftBrace,
+            TokenType::Newline, TokenType::RightBrace, TokenType::Newline, TokenType::EndOfFile]);
+    }
+
+    #[test]
+    fn braces_inside_brackets_hold_statements() {
+        assert_eq!(lex_to_tokens("[{\n}\n]\n"), vec![TokenType::LeftBracket, TokenType::LeftBrace,
+            TokenType::Newline, TokenType::RightBrace, TokenType::IgnoredNewline, TokenType::RightBracket,
+            TokenType::Newline, TokenType::EndOfFile]);
+    }
+
+    #[test]
+    fn line_continuation() {
+        assert_eq!(lex_to_tokens("a \\\nb\n"), vec![TokenType::Identifier, TokenType::Whitespace,
+            TokenType::LineContinuation, TokenType::Identifier, TokenType::Newline, TokenType::EndOfFile]);
+    }
+
+    #[test]
+    fn line_continuation_at_end_of_file() {
+        assert_eq!(lex_to_err("a \\\n"), LexErrorType::DanglingLineContinuation);
+        let (tokens, errors) = lex_recovering("a \\");
+        assert_eq!(tokens, vec![TokenType::Identifier, TokenType::Whitespace, TokenType::Error, TokenType::EndOfFile]);
+        assert_eq!(errors, vec![LexErrorType::DanglingLineContinuation, LexErrorType::MissingTrailingNewLine]);
+    }
+
+    #[test]
+    fn misplaced_line_continuation() {
+        assert_eq!(lex_to_err("a \\ b\n"), LexErrorType::MisplacedLineContinuation);
+        assert_eq!(lex_recovering("1\\b\n"), (vec![TokenType::DecimalLiteral, TokenType::Error,
+            TokenType::Identifier, TokenType::Newline, TokenType::EndOfFile],
+            vec![LexErrorType::MisplacedLineContinuation]));
+    }
+
+    #[test]
+    fn indentation_mode() {
+        let lexer = Lexer::new("my_file".into()).with_indentation();
+        let tokens: Vec<TokenType> = lexer.lex("a\n\tb\n".into()).expect("Unexpected error during test")
+            .iter().map(|x| x.token_type).collect();
+        assert_eq!(tokens, vec![TokenType::Identifier, TokenType::Newline, TokenType::Whitespace,
+            TokenType::Indent, TokenType::Identifier, TokenType::Newline, TokenType::Dedent, TokenType::EndOfFile]);
+        assert_eq!(lex_to_err("a\n\tb\n"), LexErrorType::UnexpectedCharacter);
+    }
+
+    #[test]
+    fn inconsistent_indentation_labelled() {
+        let lexer = Lexer::new("my_file".into()).with_indentation();
+        let error = lexer.lex("a\n  b\nc\n\td\n".into()).expect_err("Error not thrown when expected");
+        assert_eq!(error.error_type(), LexErrorType::InconsistentIndentation);
+        assert_eq!(error.labels()[0].message, "first indented with spaces here");
+    }
+
+    #[test]
+    fn unknown_edition() {
+        let error = lex("//! ancode: edition(9.9)\na\n").expect_err("Error not thrown when expected");
+        assert_eq!(error.error_type(), LexErrorType::UnknownEdition);
+        assert_eq!((error.span().start_line, error.span().end_index), (1, 24));
+        assert_eq!(lex_to_tokens("//! ancode: edition(0.1)\n"), vec![TokenType::LineComment, TokenType::Newline, TokenType::EndOfFile]);
+    }
+
+    #[test]
+    fn unstable_feature_gated() {
+        let source = "//! ancode: feature(indentation)\na\n  b\n";
+        let error = lex(source).expect_err("Error not thrown when expected");
+        assert_eq!(error.error_type(), LexErrorType::UnstableFeature);
+        assert_eq!(error.labels()[0].message, "enable it with --unstable-feature indentation");
+        assert_eq!(error.labels()[0].span.start_index, 20);
+
+        let lexer = Lexer::new("my_file".into()).enable_feature(UnstableFeature::Indentation);
+        let tokens = lexer.lex(source.into()).expect("Unexpected error during test");
+        assert!(tokens.iter().any(|token| token.kind() == TokenType::Indent));
+    }
+
+    #[test]
+    fn unknown_feature() {
+        assert_eq!(lex_to_err("//! ancode: feature(macros)\n"), LexErrorType::UnknownFeature);
+    }
+
+    #[test]
+    fn unfinished_literals_at_eof() {
+        assert_eq!(lex_to_err("0b"), LexErrorType::EmptyBinLiteral);
+        assert_eq!(lex_to_err("0x"), LexErrorType::EmptyHexLiteral);
+        assert_eq!(lex_to_err("1."), LexErrorType::TrailingDPoint);
+        assert_eq!(lex_recovering("1."), (vec![TokenType::Error, TokenType::EndOfFile],
+            vec![LexErrorType::TrailingDPoint, LexErrorType::MissingTrailingNewLine]));
+    }
+
+    #[test]
+    fn invalid_escape_span() {
+        let error = lex("x = \"first\nbad \\q escape\"\n").expect_err("Error not thrown when expected");
+        assert_eq!(error.span(), Span { start_line: 2, end_line: 2, start_index: 4, end_index: 6, start_byte: 4, end_byte: 6 });
+        assert!(error.to_string().ends_with("bad \\q escape\"\n    ^^\n"));
+    }
+
+    #[test]
+    fn integer_overflow() {
+        assert_eq!(lex_to_err("18446744073709551616\n"), LexErrorType::IntegerOverflow);
+        assert_eq!(lex_to_err("0x10000000000000000\n"), LexErrorType::IntegerOverflow);
+    }
+
+    #[test]
+    fn error_accessors() {
+        let error = lex("let x = 0b12\n").expect_err("Error not thrown when expected");
+        assert_eq!(error.error_type(), LexErrorType::MalformedBinLiteral);
+        assert_eq!(error.file(), "my_file");
+        assert_eq!(error.partial_token(), "0b12");
+        assert_eq!(error.span(), Span { start_line: 1, end_line: 1, start_index: 8, end_index: 12, start_byte: 8, end_byte: 12 });
+    }
+
+    #[test]
+    fn cancelled() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let lexer = Lexer::new("my_file".into()).with_cancellation(cancellation);
+        let error = lexer.lex("let x = 5\n".into()).expect_err("Error not thrown when expected");
+        assert_eq!(error.error_type(), LexErrorType::Cancelled);
+    }
+
+    #[test]
+    fn recovery_error_token() {
+        assert_eq!(lex_recovering("0b12+3\n"),
+            (vec![TokenType::Error, TokenType::Operator(Operator::Plus), TokenType::DecimalLiteral,
+                TokenType::Newline, TokenType::EndOfFile],
+            vec![LexErrorType::MalformedBinLiteral]));
+    }
+
+    #[test]
+    fn recovery_multiple_errors() {
+        assert_eq!(lex_recovering("0x\n'a \n"),
+            (vec![TokenType::Error, TokenType::Newline, TokenType::Error, TokenType::Identifier,
+                TokenType::Whitespace, TokenType::Newline, TokenType::EndOfFile],
+            vec![LexErrorType::EmptyHexLiteral, LexErrorType::WrongQuotes, LexErrorType::TrailingWhitespace]));
+    }
+
+    #[test]
+    fn recovery_continues_string() {
+        let (tokens, errors) = lex_recovering("\"bad \\q\" x\n");
+        assert_eq!(tokens[0], TokenType::StringLiteral);
+        assert_eq!(tokens[2], TokenType::Identifier);
+        assert_eq!(errors, vec![LexErrorType::InvalidEscape]);
+    }
+
+    #[test]
+    fn recovery_at_eof() {
+        assert_eq!(lex_recovering("x"),
+            (vec![TokenType::Identifier, TokenType::EndOfFile], vec![LexErrorType::MissingTrailingNewLine]));
+    }
+
+    #[test]
+    fn allowed_by_directive() {
+        let tokens = lex_to_tokens("//! ancode: allow(trailing_whitespace, missing_trailing_newline)\nx \ny");
+        assert_eq!(tokens[tokens.len() - 2], TokenType::Identifier);
+        assert_eq!(lex_to_tokens("//! ancode: allow(trailing_whitespace)\n// comment \n").len(), 5);
+    }
+
+    #[test]
+    fn allowed_by_caller() {
+        let lexer = Lexer::new("my_file".into()).allow(LexErrorType::MissingTrailingNewLine);
+        assert!(lexer.lex("x".into()).is_ok());
+        assert_eq!(lex_to_err("//! ancode: allow(missing_trailing_newline)\nx \n"), LexErrorType::TrailingWhitespace);
+    }
+
+    #[test]
+    fn directive_inside_string_ignored() {
+        assert_eq!(lex_to_err("x = \"\n//! ancode: allow(trailing_whitespace)\n\"\ny \n"), LexErrorType::TrailingWhitespace);
+    }
+
+    #[test]
+    fn allowed_missing_newline_still_checks_literals() {
+        assert_eq!(lex_to_err("//! ancode: allow(missing_trailing_newline)\n1."), LexErrorType::TrailingDPoint);
+        assert_eq!(lex_to_err("//! ancode: allow(missing_trailing_newline)\n0b"), LexErrorType::EmptyBinLiteral);
+        assert_eq!(lex_to_values("//! ancode: allow(missing_trailing_newline)\n0b11"), vec![LiteralValue::Integer(3)]);
+    }
+
+    #[test]
+    fn missing_newline_after_self_terminating_token() {
+        for source in ["x = (1)", "{}", "x +", "\"a\""] {
+            assert_eq!(lex_to_err(source), LexErrorType::MissingTrailingNewLine, "{:?}", source);
+        }
+        assert!(lex("").is_ok());
+    }
+
+    #[test]
+    fn style_checks_are_warnings() {
+        let error = lex("x \n").expect_err("Error not thrown when expected");
+        assert!(error.error_type().is_warning());
+        assert!(error.to_string().starts_with("Warning while lexing file my_file\n"));
+        assert!(!LexErrorType::MalformedBinLiteral.is_warning());
+    }
+
+    #[test]
+    fn render_with_context() {
+        let error = lex("1\n0b2\n3\n").expect_err("Error not thrown when expected");
+        assert_eq!(error.render(1), "Error while lexing file my_file\nMalformed binary literal on line 2, index 0-3:\n\
+            1 | 1\n2 | 0b2\n  | ^^^\n3 | 3\n");
+    }
+
+    #[test]
+    fn labels_shown_with_error() {
+        let error = lex("(\n0b2\n").expect_err("Error not thrown when expected")
+            .with_label(Span { start_line: 1, end_line: 1, start_index: 0, end_index: 1, start_byte: 0, end_byte: 1 }, "opened here");
+        assert!(error.to_string().ends_with("1 | (\n  | - opened here\n2 | 0b2\n  | ^^^\n"));
+    }
+
+    #[test]
+    fn wide_character_columns() {
+        let error = lex("\"日本\" 0b2\n").expect_err("Error not thrown when expected");
+        assert_eq!((error.span().start_index, error.span().start_byte), (5, 9));
+        assert!(error.to_string().ends_with("\"日本\" 0b2\n       ^^^\n"));
+    }
+
     #[test]
     fn trailing_whitespace_string() {
         lex_to_tokens("let x = \"This is a string literal \nwith intentional trailing \nwhitespace\"\n");
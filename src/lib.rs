@@ -1,3 +1,32 @@
+#![allow(clippy::needless_return)]
+//LexError carries the source text and span so it can render itself, which is fine to move around
+#![allow(clippy::result_large_err)]
+
+//the git commit and build date are included so bytecode and caches can check compatibility
+pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"),
+    " (", env!("ANCODE_GIT_HASH"), " ", env!("ANCODE_BUILD_DATE"), ")");
+
+pub mod cancel;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod compile;
+pub mod delimiters;
+pub mod delta_debug;
+pub mod diagnostic;
+pub mod diff;
+pub mod directives;
+pub mod edition;
+pub mod features;
+pub mod grammar;
+pub mod indentation;
+pub mod json;
+pub mod lexer;
+pub mod log;
+pub mod paths;
+pub mod session;
+pub mod timing;
+pub mod width;
+
 #[cfg(test)]
 mod tests {
     // #[test]
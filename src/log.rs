@@ -0,0 +1,141 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub enum Level {
+    Off,
+    Info,
+    Debug,
+    Trace,
+}
+impl Level {
+    //-v is info, -vv is debug and anything more is trace
+    pub fn from_verbosity(count: u8) -> Level {
+        match count {
+            0 => Level::Off,
+            1 => Level::Info,
+            2 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name.to_lowercase().as_str() {
+            "off" => Some(Level::Off),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Level::Off => write!(f, "OFF"),
+            Level::Info => write!(f, "INFO"),
+            Level::Debug => write!(f, "DEBUG"),
+            Level::Trace => write!(f, "TRACE"),
+        }
+    }
+}
+
+pub const LOG_ENV_VAR: &str = "ANCODE_LOG";
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Off as u8);
+
+thread_local! {
+    static PHASES: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level_from_env() -> Option<Level> {
+    return std::env::var(LOG_ENV_VAR).ok().and_then(|name| Level::from_name(&name));
+}
+
+pub fn enabled(level: Level) -> bool {
+    return level != Level::Off && level as u8 <= LEVEL.load(Ordering::Relaxed);
+}
+
+//called through the logging macros, which check the level before formatting anything
+pub fn write(level: Level, args: std::fmt::Arguments) {
+    let phases = PHASES.with(|phases| phases.borrow().join("/"));
+    if phases.is_empty() {
+        eprintln!("[{}] {}", level, args);
+    } else {
+        eprintln!("[{} {}] {}", level, phases, args);
+    }
+}
+
+//logs entering and leaving a compiler phase, prefixes messages logged in between with its name,
+//and records how long it took for --time-passes
+pub struct PhaseGuard {
+    name: &'static str,
+    started: Instant,
+}
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        crate::info!("finished {}", self.name);
+        let depth = PHASES.with(|phases| {
+            let mut phases = phases.borrow_mut();
+            phases.pop();
+            phases.len()
+        });
+        crate::timing::record(self.name, depth, self.started);
+    }
+}
+
+pub fn enter_phase(name: &'static str) -> PhaseGuard {
+    PHASES.with(|phases| phases.borrow_mut().push(name));
+    crate::info!("starting {}", name);
+    return PhaseGuard { name, started: crate::timing::start_pass() };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Info) {
+            $crate::log::write($crate::log::Level::Info, format_args!($($arg)*))
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Debug) {
+            $crate::log::write($crate::log::Level::Debug, format_args!($($arg)*))
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Trace) {
+            $crate::log::write($crate::log::Level::Trace, format_args!($($arg)*))
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_levels() {
+        assert_eq!(Level::from_verbosity(0), Level::Off);
+        assert_eq!(Level::from_verbosity(2), Level::Debug);
+        assert_eq!(Level::from_verbosity(5), Level::Trace);
+    }
+
+    #[test]
+    fn level_names() {
+        assert_eq!(Level::from_name("TRACE"), Some(Level::Trace));
+        assert_eq!(Level::from_name("loud"), None);
+    }
+}
@@ -1,58 +1,338 @@
-use clap::Parser;
+#![allow(clippy::needless_return)]
+
+use clap::{Parser, Subcommand, ValueEnum};
 use std::fs::File;
 use std::io::{ErrorKind, Read};
 use std::io::Error;
+use std::process::ExitCode;
+
+use compiler::lexer::{LexError, LexErrorType};
+use compiler::paths::{self, PathRemapping};
+use compiler::edition::LanguageVersion;
+use compiler::features::UnstableFeature;
+use compiler::session::{Options, Session};
+use compiler::{diagnostic, diff, grammar, json, log, timing};
 
-mod lexer;
-use crate::lexer::{Token, LexError, Lexer};
+mod check;
+mod ice;
+mod reduce;
 
-// #[command(author, version)]
 #[derive(Parser, Debug)]
+#[command(author, version = compiler::VERSION)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    entry_file: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The file to compile when no subcommand is given
+    #[arg(required = true)]
+    entry_file: Option<String>,
+    /// Print every token of the entry file
     #[arg(short, long)]
     lexer_debug: bool,
+    /// Log progress: -v for phases, -vv for details, -vvv to trace every token
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Print how long each compiler pass took
+    #[arg(long, global = true)]
+    time_passes: bool,
+    /// Write a Chrome trace-event JSON of the compiler passes to this file
+    #[arg(long, global = true, value_name = "FILE")]
+    profile: Option<String>,
+    /// Replace a path prefix in all output, for builds that don't depend on where they were run
+    #[arg(long, global = true, value_name = "FROM=TO")]
+    remap_path_prefix: Vec<PathRemapping>,
+    /// Print results as JSON on stdout and diagnostics as JSON lines on stderr
+    #[arg(long, global = true)]
+    json: bool,
+    /// Treat warnings as errors for the exit status
+    #[arg(long, global = true)]
+    fail_on_warning: bool,
+    /// Show this many source lines either side of each error, with line numbers
+    #[arg(long, global = true, value_name = "LINES")]
+    diagnostic_context: Option<usize>,
+    /// The language version for files without an edition directive
+    #[arg(long, global = true, value_name = "VERSION", default_value_t = LanguageVersion::LATEST)]
+    edition: LanguageVersion,
+    /// Let files turn on an experimental feature with a `//! ancode: feature(NAME)` directive
+    #[arg(long, global = true, value_name = "NAME")]
+    unstable_feature: Vec<UnstableFeature>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check every .an file in a directory, grouping the diagnostics by file
+    Check {
+        /// A source file, or a directory to search for .an files
+        path: String,
+    },
+    /// Print the token grammar the lexer implements
+    Grammar {
+        #[arg(long, value_enum, default_value_t = GrammarFormat::Ebnf)]
+        format: GrammarFormat,
+    },
+    /// Compare two files token by token, ignoring whitespace and comments. Exits 1 if they differ
+    Diff {
+        old: String,
+        new: String,
+    },
+    /// Shrink a file while a check command keeps failing the same way, for bug reports
+    Reduce {
+        /// The file that reproduces the problem
+        file: String,
+        /// Shell command run with the candidate file appended. The candidate is kept while the
+        /// command exits with the same status as it does for the original file
+        #[arg(long)]
+        check: String,
+        /// Write the reduced file here instead of printing it
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Print a syntax highlighting grammar for editors that don't use the language server
+    EmitGrammar {
+        #[arg(long, value_enum, default_value_t = EditorGrammarFormat::Textmate)]
+        format: EditorGrammarFormat,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum EditorGrammarFormat {
+    Textmate,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GrammarFormat {
+    Ebnf,
+    Json,
+}
+
+//ordered by severity, so combining results from several files is just taking the max
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ExitStatus {
+    Success = 0,
+    CompileErrors = 1,
+    UsageError = 2,
+    InternalError = 3,
+}
+impl ExitStatus {
+    pub fn from_lex_error(lex_error: &LexError) -> ExitStatus {
+        if lex_error.error_type() == LexErrorType::InternalError {
+            return ExitStatus::InternalError;
+        } else if lex_error.error_type().is_warning() {
+            return ExitStatus::Success;
+        } else {
+            return ExitStatus::CompileErrors;
+        }
+    }
+
+    pub fn from_lex_errors(lex_errors: &[LexError]) -> ExitStatus {
+        return lex_errors.iter().map(ExitStatus::from_lex_error).max().unwrap_or(ExitStatus::Success);
+    }
+
+    //warnings only fail the run when --fail-on-warning asks them to
+    pub fn failing_on_warnings(self, warnings: usize, fail_on_warning: bool) -> ExitStatus {
+        if fail_on_warning && warnings > 0 {
+            return self.max(ExitStatus::CompileErrors);
+        }
+        return self;
+    }
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Args::parse();
+    ice::install_hook();
+    if args.verbose > 0 {
+        log::set_level(log::Level::from_verbosity(args.verbose));
+    } else if let Some(level) = log::level_from_env() {
+        log::set_level(level);
+    }
+    if args.time_passes || args.profile.is_some() {
+        timing::enable();
+    }
 
-    let entry_file_result = File::open(&args.entry_file);
-    match entry_file_result {
-        Ok(mut main_file) => {
+    let session = Session::new(Options {
+        remappings: args.remap_path_prefix.clone(),
+        recovery: true,
+        version: args.edition,
+        features: args.unstable_feature.clone(),
+        ..Options::default()
+    });
+
+    let status = match &args.command {
+        Some(Command::Check { path }) => {
+            check::check_path(path, &session, args.json, args.diagnostic_context).exit_status(args.fail_on_warning)
+        },
+        Some(Command::Grammar { format }) => {
+            match format {
+                GrammarFormat::Ebnf if !args.json => print!("{}", grammar::to_ebnf()),
+                GrammarFormat::Ebnf | GrammarFormat::Json => print!("{}", grammar::to_json()),
+            }
+            ExitStatus::Success
+        },
+        Some(Command::Diff { old, new }) => {
+            diff_files(old, new, args.json, &session)
+        },
+        Some(Command::Reduce { file, check, output }) => {
+            reduce::reduce_file(file, check, output.as_deref(), args.json)
+        },
+        Some(Command::EmitGrammar { format }) => {
+            match format {
+                EditorGrammarFormat::Textmate => print!("{}", grammar::to_textmate()),
+            }
+            ExitStatus::Success
+        },
+        None => {
+            match &args.entry_file {
+                Some(entry_file) => lex_entry_file(entry_file, &args, &session),
+                //clap requires the entry file when there's no subcommand
+                None => ExitStatus::UsageError,
+            }
+        }
+    };
+
+    let status = status.max(report_timings(&args));
+    return ExitCode::from(status as u8);
+}
+
+fn lex_entry_file(entry_file: &str, args: &Args, session: &Session) -> ExitStatus {
+    let display_name = paths::remap_path(entry_file, &session.options().remappings);
+    match read_source_file(entry_file, &display_name) {
+        Ok(file_string) => {
             //do compiler stuff here
-            let mut file_string = String::new();
-            let file_result = main_file.read_to_string(&mut file_string);
-            match file_result {
-                Ok(_) => {
-                    let lexer = Lexer::new(args.entry_file);
-                    let tokens_result: Result<Vec<Token>,LexError> = lexer.lex(file_string);
-
-                    match tokens_result {
-                        Ok(tokens) => {
-                            if args.lexer_debug {
-                                println!("There are {} tokens", tokens.len());
-                                println!("[DEBUG] Tokens:");
-                                for token in tokens {
-                                    println!("{}", token)
-                                }
-                            }
-                        },
-                        Err(lex_error) => {
-                            print!("{}", lex_error.to_string())
-                        }
-                    }
-
-                },
-                Err(file_error) => {
-                    deal_with_file_error(file_error, args.entry_file)
+            ice::set_phase("lexing", &display_name);
+            let source = session.add_source(entry_file, file_string);
+            let tokens = session.lex(source);
+            let diagnostics = diagnostic::deduplicate(session.take_diagnostics());
+            let warnings = diagnostics.iter().filter(|diagnostic| diagnostic.error_type().is_warning()).count();
+            let has_errors = diagnostics.len() > warnings;
+            let status = ExitStatus::from_lex_errors(&diagnostics).failing_on_warnings(warnings, args.fail_on_warning);
+            print_lex_errors(&diagnostics, args.json, args.diagnostic_context);
+
+            if args.json {
+                let mut fields = vec![
+                    ("file", json::string(&display_name)),
+                    ("success", (status == ExitStatus::Success).to_string()),
+                ];
+                //warnings don't stop the file from lexing, so its tokens are still worth showing
+                if !has_errors {
+                    let tokens: Vec<String> = tokens.iter().map(json::token).collect();
+                    fields.push(("tokens", json::array(&tokens)));
+                }
+                println!("{}", json::object(&fields));
+            } else if args.lexer_debug && !has_errors {
+                println!("There are {} tokens", tokens.len());
+                println!("[DEBUG] Tokens:");
+                for token in tokens {
+                    println!("{}", token)
                 }
             }
+            return status;
         },
-        Err(error) => {
-            deal_with_file_error(error, args.entry_file);
+        Err(file_error) if args.json => {
+            eprintln!("{}", json::io_error(&display_name, &file_error));
+            println!("{}", json::object(&[
+                ("file", json::string(&display_name)),
+                ("success", "false".into()),
+            ]));
+            return ExitStatus::UsageError;
+        },
+        Err(file_error) => {
+            deal_with_file_error(file_error, display_name);
+            return ExitStatus::UsageError;
+        }
+    }
+}
+
+//prints every error from lexing a file, summarising repeats of the same error after the first
+//few. JSON output isn't summarised, since tools can group the errors themselves
+pub fn print_lex_errors(lex_errors: &[LexError], json_output: bool, context: Option<usize>) {
+    if json_output {
+        for lex_error in lex_errors {
+            eprintln!("{}", json::lex_error(lex_error));
+        }
+        return;
+    }
+    let (shown, hidden) = diagnostic::group_similar(lex_errors, diagnostic::MAX_SIMILAR_ERRORS);
+    for (error_num, lex_error) in shown.iter().enumerate() {
+        if error_num > 0 {
+            println!();
+        }
+        print!("{}", render_lex_error(lex_error, context));
+    }
+    for (error_type, count) in hidden {
+        println!("... and {} more similar errors: {}", count, error_type);
+    }
+}
+
+pub fn render_lex_error(lex_error: &LexError, context: Option<usize>) -> String {
+    match context {
+        Some(context) => return lex_error.render(context),
+        None => return lex_error.to_string(),
+    }
+}
+
+fn diff_files(old: &str, new: &str, json_output: bool, session: &Session) -> ExitStatus {
+    let mut token_streams = Vec::new();
+    for file in [old, new] {
+        let display_name = paths::remap_path(file, &session.options().remappings);
+        match read_source_file(file, &display_name) {
+            //lex errors are compared like any other token, since the files may not be finished
+            Ok(source) => token_streams.push(session.lex(session.add_source(file, source))),
+            Err(file_error) if json_output => {
+                eprintln!("{}", json::io_error(&display_name, &file_error));
+                return ExitStatus::UsageError;
+            },
+            Err(file_error) => {
+                deal_with_file_error(file_error, display_name);
+                return ExitStatus::UsageError;
+            }
         }
     }
+    let ops = diff::diff_tokens(&token_streams[0], &token_streams[1]);
+    if json_output {
+        println!("{}", json::object(&[
+            ("old", json::string(&paths::remap_path(old, &session.options().remappings))),
+            ("new", json::string(&paths::remap_path(new, &session.options().remappings))),
+            ("changes", json::diff(&ops)),
+        ]));
+    } else {
+        print!("{}", diff::render_diff(&ops));
+    }
+    //like diff(1), differences are reported through the exit status so scripts can check for them
+    if diff::has_changes(&ops) {
+        return ExitStatus::CompileErrors;
+    }
+    return ExitStatus::Success;
+}
+
+fn read_source_file(file_name: &str, display_name: &str) -> Result<String, Error> {
+    let _phase = log::enter_phase("read");
+    ice::set_phase("reading", display_name);
+    let mut file_string = String::new();
+    File::open(file_name)?.read_to_string(&mut file_string)?;
+    return Ok(file_string);
+}
+
+fn report_timings(args: &Args) -> ExitStatus {
+    if !args.time_passes && args.profile.is_none() {
+        return ExitStatus::Success;
+    }
+    let timings = timing::take();
+    if args.time_passes {
+        eprint!("{}", timing::render_table(&timings));
+    }
+    if let Some(profile_file) = &args.profile {
+        if let Err(error) = std::fs::write(profile_file, timing::chrome_trace_json(&timings)) {
+            //on stderr, since stdout may be JSON that this would corrupt
+            if args.json {
+                eprintln!("{}", json::io_error(profile_file, &error));
+            } else {
+                eprintln!("Could not write profile to '{}'", profile_file);
+                eprintln!("{}", error);
+            }
+            return ExitStatus::UsageError;
+        }
+    }
+    return ExitStatus::Success;
 }
 
 fn deal_with_file_error(file_error: Error, file_name: String) {
@@ -69,4 +349,55 @@ fn deal_with_file_error(file_error: Error, file_name: String) {
             println!("{}", file_error);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_flags_before_and_after_subcommand() {
+        for argv in [["compiler", "--json", "-v", "check", "dir"], ["compiler", "check", "dir", "--json", "-v"]] {
+            let args = Args::try_parse_from(argv).expect("Arguments failed to parse");
+            assert!(matches!(args.command, Some(Command::Check { ref path }) if path == "dir"));
+            assert!(args.json);
+            assert_eq!(args.verbose, 1);
+        }
+    }
+
+    #[test]
+    fn global_flag_before_subcommand_without_arguments() {
+        let args = Args::try_parse_from(["compiler", "--time-passes", "grammar"]).expect("Arguments failed to parse");
+        assert!(matches!(args.command, Some(Command::Grammar { .. })));
+        assert!(args.time_passes);
+    }
+
+    #[test]
+    fn entry_file_without_subcommand() {
+        let args = Args::try_parse_from(["compiler", "--json", "main.an"]).expect("Arguments failed to parse");
+        assert!(args.command.is_none());
+        assert_eq!(args.entry_file.as_deref(), Some("main.an"));
+        assert!(Args::try_parse_from(["compiler", "--json"]).is_err());
+    }
+
+    #[test]
+    fn every_command_and_flag_has_help() {
+        use clap::CommandFactory;
+        let command = Args::command();
+        for subcommand in command.get_subcommands() {
+            assert!(subcommand.get_about().is_some(), "{} has no help", subcommand.get_name());
+        }
+        for arg in command.get_arguments() {
+            assert!(arg.get_help().is_some(), "--{} has no help", arg.get_id());
+        }
+    }
+
+    #[test]
+    fn warnings_only_fail_when_asked() {
+        let errors = compiler::lexer::Lexer::new("my_file".into()).lex_with_errors("x \n".into()).1;
+        assert_eq!(ExitStatus::from_lex_errors(&errors), ExitStatus::Success);
+        assert_eq!(ExitStatus::Success.failing_on_warnings(1, false), ExitStatus::Success);
+        assert_eq!(ExitStatus::Success.failing_on_warnings(1, true), ExitStatus::CompileErrors);
+        assert_eq!(ExitStatus::UsageError.failing_on_warnings(1, true), ExitStatus::UsageError);
+    }
+}
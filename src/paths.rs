@@ -0,0 +1,94 @@
+//rewrites path prefixes in everything the compiler outputs, so the output doesn't depend on
+//where the project was checked out
+#[derive(Debug, PartialEq, Clone)]
+pub struct PathRemapping {
+    from: String,
+    to: String,
+}
+impl std::str::FromStr for PathRemapping {
+    type Err = String;
+
+    fn from_str(mapping: &str) -> Result<PathRemapping, String> {
+        match mapping.split_once('=') {
+            Some((from, to)) if !from.is_empty() => {
+                return Ok(PathRemapping { from: from.to_string(), to: to.to_string() })
+            },
+            _ => return Err(format!("expected FROM=TO, found '{}'", mapping)),
+        }
+    }
+}
+
+//like rustc, later mappings take priority over earlier ones
+pub fn remap_path(path: &str, remappings: &[PathRemapping]) -> String {
+    for remapping in remappings.iter().rev() {
+        if let Some(rest) = path.strip_prefix(&remapping.from) {
+            return remapping.to.clone() + rest;
+        }
+    }
+    return path.to_string();
+}
+
+pub const SOURCE_EXTENSION: &str = "an";
+
+//every .an file under the given path, sorted so the order doesn't depend on the filesystem
+pub fn find_source_files(path: &std::path::Path) -> std::io::Result<Vec<std::path::PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    let mut entries = std::fs::read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let entry_path = entry.path();
+        //symlinks aren't followed into directories, since one pointing back up would never end
+        if entry.file_type()?.is_dir() {
+            files.extend(find_source_files(&entry_path)?);
+        } else if entry_path.extension().is_some_and(|extension| extension == SOURCE_EXTENSION) {
+            files.push(entry_path);
+        }
+    }
+    return Ok(files);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaps_prefix() {
+        let remappings = vec!["/home/me/project=.".parse().unwrap()];
+        assert_eq!(remap_path("/home/me/project/src/main.an", &remappings), "./src/main.an");
+        assert_eq!(remap_path("/elsewhere/main.an", &remappings), "/elsewhere/main.an");
+    }
+
+    #[test]
+    fn later_mapping_wins() {
+        let remappings: Vec<PathRemapping> = vec!["/home=/a".parse().unwrap(), "/home/me=/b".parse().unwrap()];
+        assert_eq!(remap_path("/home/me/x.an", &remappings), "/b/x.an");
+    }
+
+    #[test]
+    fn finds_examples() {
+        let examples = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+        let files: Vec<String> = find_source_files(&examples).unwrap().iter()
+            .map(|file| file.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(files, vec!["decimal.an", "identifiers.an", "test.an", "unclosed_string.an"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_not_followed() {
+        let root = std::env::temp_dir().join(format!("ancode-paths-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::write(root.join("a").join("x.an"), "x\n").unwrap();
+        let _ = std::os::unix::fs::symlink("..", root.join("a").join("back"));
+        let files = find_source_files(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(files.unwrap(), vec![root.join("a").join("x.an")]);
+    }
+
+    #[test]
+    fn rejects_missing_separator() {
+        assert!("/home/me".parse::<PathRemapping>().is_err());
+    }
+}
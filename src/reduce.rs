@@ -0,0 +1,79 @@
+use std::process::{Command, Stdio};
+
+use compiler::{delta_debug, json};
+
+use crate::ExitStatus;
+
+//with json_output, errors are JSON lines on stderr and the result is one JSON object on stdout
+pub fn reduce_file(file: &str, check: &str, output: Option<&str>, json_output: bool) -> ExitStatus {
+    let source = match std::fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(error) if json_output => {
+            eprintln!("{}", json::io_error(file, &error));
+            return ExitStatus::UsageError;
+        },
+        Err(error) => {
+            crate::deal_with_file_error(error, file.to_string());
+            return ExitStatus::UsageError;
+        }
+    };
+    let candidate_file = std::env::temp_dir().join(format!("ancode-reduce-{}.an", std::process::id()));
+    let candidate_name = candidate_file.to_string_lossy().to_string();
+
+    let run_check = |candidate: &str| -> Option<i32> {
+        std::fs::write(&candidate_file, candidate).ok()?;
+        let status = Command::new("sh").arg("-c").arg(format!("{} \"$1\"", check)).arg("sh").arg(&candidate_name)
+            .stdout(Stdio::null()).stderr(Stdio::null()).status().ok()?;
+        return status.code();
+    };
+
+    let original_status = run_check(&source);
+    if original_status.is_none() {
+        if json_output {
+            eprintln!("{}", json::object(&[
+                ("error_type", json::string("check_not_run")),
+                ("message", json::string(&format!("Could not run check command '{}'", check))),
+            ]));
+        } else {
+            println!("Could not run check command '{}'", check);
+        }
+        let _ = std::fs::remove_file(&candidate_file);
+        return ExitStatus::UsageError;
+    }
+    let units = delta_debug::reduction_units(&source);
+    let mut tests_run = 0;
+    let reduced = delta_debug::ddmin(units, |candidate| {
+        tests_run += 1;
+        return run_check(&candidate.concat()) == original_status;
+    }).concat();
+    let _ = std::fs::remove_file(&candidate_file);
+    if !json_output {
+        eprintln!("Reduced {} bytes to {} bytes in {} runs of the check", source.len(), reduced.len(), tests_run);
+    }
+
+    if let Some(output) = output {
+        if let Err(error) = std::fs::write(output, &reduced) {
+            if json_output {
+                eprintln!("{}", json::io_error(output, &error));
+            } else {
+                println!("Could not write reduced file to '{}'", output);
+                println!("{}", error);
+            }
+            return ExitStatus::UsageError;
+        }
+    }
+    if json_output {
+        println!("{}", json::object(&[
+            ("file", json::string(file)),
+            ("original_bytes", source.len().to_string()),
+            ("reduced_bytes", reduced.len().to_string()),
+            ("runs", tests_run.to_string()),
+            //the reduced source is only inline when it wasn't written to a file
+            ("output", output.map_or("null".to_string(), json::string)),
+            ("reduced", if output.is_some() { "null".to_string() } else { json::string(&reduced) }),
+        ]));
+    } else if output.is_none() {
+        print!("{}", reduced);
+    }
+    return ExitStatus::Success;
+}
@@ -0,0 +1,144 @@
+//everything a compilation shares: the options, the source files and the diagnostics reported so
+//far. It is Send + Sync so a language server or a parallel build can lex files from several
+//threads into one session. There is no interner yet, since nothing after lexing uses names.
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::cancel::CancellationToken;
+use crate::edition::LanguageVersion;
+use crate::features::UnstableFeature;
+use crate::lexer::{LexError, LexErrorType, Lexer, Token};
+use crate::paths::{self, PathRemapping};
+
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    pub remappings: Vec<PathRemapping>,
+    //keep lexing after an error, see Lexer::with_recovery
+    pub recovery: bool,
+    //see Lexer::with_indentation
+    pub indentation: bool,
+    //style checks to skip in every file, see Lexer::allow
+    pub allowed: Vec<LexErrorType>,
+    pub cancellation: Option<CancellationToken>,
+    //for files that don't pin their own edition
+    pub version: LanguageVersion,
+    //unstable features files may turn on, see Lexer::enable_feature
+    pub features: Vec<UnstableFeature>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SourceId(usize);
+
+#[derive(Debug)]
+pub struct SourceFile {
+    //the path as given, for reading the file again
+    pub path: String,
+    //the path after remapping, for everything the compiler outputs
+    pub name: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Session {
+    options: Options,
+    sources: RwLock<Vec<Arc<SourceFile>>>,
+    diagnostics: Mutex<Vec<LexError>>,
+}
+
+impl Session {
+    pub fn new(options: Options) -> Session {
+        return Session { options, sources: RwLock::new(Vec::new()), diagnostics: Mutex::new(Vec::new()) };
+    }
+
+    pub fn options(&self) -> &Options {
+        return &self.options;
+    }
+
+    pub fn add_source(&self, path: &str, contents: String) -> SourceId {
+        let name = paths::remap_path(path, &self.options.remappings);
+        let mut sources = self.sources.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        sources.push(Arc::new(SourceFile { path: path.to_string(), name, contents }));
+        return SourceId(sources.len() - 1);
+    }
+
+    pub fn source(&self, id: SourceId) -> Arc<SourceFile> {
+        let sources = self.sources.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        return sources[id.0].clone();
+    }
+
+    //a lexer for the file, set up from the session's options
+    pub fn lexer(&self, id: SourceId) -> Lexer {
+        let mut lexer = Lexer::new(self.source(id).name.clone()).with_version(self.options.version);
+        if self.options.recovery {
+            lexer = lexer.with_recovery();
+        }
+        if self.options.indentation {
+            lexer = lexer.with_indentation();
+        }
+        if let Some(cancellation) = &self.options.cancellation {
+            lexer = lexer.with_cancellation(cancellation.clone());
+        }
+        for feature in &self.options.features {
+            lexer = lexer.enable_feature(*feature);
+        }
+        for error_type in &self.options.allowed {
+            lexer = lexer.allow(*error_type);
+        }
+        return lexer;
+    }
+
+    //lexes the file, reporting any errors to the session
+    pub fn lex(&self, id: SourceId) -> Vec<Token> {
+        let (tokens, errors) = self.lexer(id).lex_with_errors(self.source(id).contents.clone());
+        for error in errors {
+            self.emit(error);
+        }
+        return tokens;
+    }
+
+    pub fn emit(&self, error: LexError) {
+        self.diagnostics.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(error);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        return !self.diagnostics.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).is_empty();
+    }
+
+    //the diagnostics reported so far, in the order they were reported
+    pub fn take_diagnostics(&self) -> Vec<LexError> {
+        return std::mem::take(&mut *self.diagnostics.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn session_is_send_and_sync() {
+        assert_send_sync::<Session>();
+    }
+
+    #[test]
+    fn remaps_source_names() {
+        let session = Session::new(Options { remappings: vec!["/home/me=.".parse().unwrap()], ..Options::default() });
+        let id = session.add_source("/home/me/main.an", "a\n".into());
+        assert_eq!(session.source(id).name, "./main.an");
+        assert_eq!(session.source(id).path, "/home/me/main.an");
+    }
+
+    #[test]
+    fn collects_diagnostics_from_threads() {
+        let session = Session::new(Options { recovery: true, ..Options::default() });
+        let ids: Vec<SourceId> = (0..4).map(|n| session.add_source(&format!("{}.an", n), "0b2\n".into())).collect();
+        std::thread::scope(|scope| {
+            for id in ids {
+                let session = &session;
+                scope.spawn(move || session.lex(id));
+            }
+        });
+        assert_eq!(session.take_diagnostics().len(), 4);
+        assert!(!session.has_errors());
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub depth: usize,
+    //offset from the first recorded pass, so the trace starts at zero
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+static TIMINGS: Mutex<Vec<PassTiming>> = Mutex::new(Vec::new());
+//off by default, so library users and long running processes don't build up timings nobody reads
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn start_pass() -> Instant {
+    let now = Instant::now();
+    EPOCH.get_or_init(|| now);
+    return now;
+}
+
+pub fn record(name: &'static str, depth: usize, started: Instant) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let epoch = *EPOCH.get_or_init(|| started);
+    let timing = PassTiming {
+        name,
+        depth,
+        start: started.saturating_duration_since(epoch),
+        duration: started.elapsed(),
+    };
+    if let Ok(mut timings) = TIMINGS.lock() {
+        timings.push(timing);
+    }
+}
+
+//passes are recorded as they finish, so this sorts them back into the order they started
+pub fn take() -> Vec<PassTiming> {
+    let mut timings = match TIMINGS.lock() {
+        Ok(mut timings) => std::mem::take(&mut *timings),
+        Err(_) => Vec::new(),
+    };
+    timings.sort_by_key(|timing| (timing.start, timing.depth));
+    return timings;
+}
+
+pub fn render_table(timings: &[PassTiming]) -> String {
+    let mut table = String::new();
+    for timing in timings {
+        table += &format!("time: {:>10.3}ms  {}{}\n",
+            timing.duration.as_secs_f64() * 1000.0,
+            "  ".repeat(timing.depth),
+            timing.name);
+    }
+    return table;
+}
+
+//the Chrome trace event format, which chrome://tracing and speedscope can load
+pub fn chrome_trace_json(timings: &[PassTiming]) -> String {
+    let events: Vec<String> = timings.iter().map(|timing| format!(
+        "{{\"name\":\"{}\",\"cat\":\"pass\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":1,\"tid\":1}}",
+        timing.name, timing.start.as_micros(), timing.duration.as_micros()
+    )).collect();
+    return format!("{{\"traceEvents\":[{}]}}\n", events.join(","));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_recorded_unless_enabled() {
+        record("lex", 0, start_pass());
+        assert_eq!(take(), vec![]);
+    }
+
+    #[test]
+    fn chrome_trace_format() {
+        let timings = vec![PassTiming {
+            name: "lex", depth: 0,
+            start: Duration::from_micros(5), duration: Duration::from_micros(20),
+        }];
+        assert_eq!(chrome_trace_json(&timings),
+            "{\"traceEvents\":[{\"name\":\"lex\",\"cat\":\"pass\",\"ph\":\"X\",\"ts\":5,\"dur\":20,\"pid\":1,\"tid\":1}]}\n");
+    }
+}
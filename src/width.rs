@@ -0,0 +1,47 @@
+//terminal display width of text, so carets line up under wide characters like CJK and emoji.
+//This covers the common ranges rather than the full Unicode East Asian Width tables.
+pub fn char_width(c: char) -> usize {
+    let code = c as u32;
+    match code {
+        //combining marks and zero width characters
+        0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200B..=0x200F |
+        0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F => 0,
+        //wide and fullwidth characters
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF |
+        0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF |
+        0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x20000..=0x3FFFD => 2,
+        //symbols shown as emoji by default. Most of U+2600..27BF is narrow, so only these are wide
+        0x231A..=0x231B | 0x2329..=0x232A | 0x23E9..=0x23EC | 0x23F0 | 0x23F3 | 0x25FD..=0x25FE |
+        0x2614..=0x2615 | 0x2648..=0x2653 | 0x267F | 0x2693 | 0x26A1 | 0x26AA..=0x26AB |
+        0x26BD..=0x26BE | 0x26C4..=0x26C5 | 0x26CE | 0x26D4 | 0x26EA | 0x26F2..=0x26F3 | 0x26F5 |
+        0x26FA | 0x26FD | 0x2705 | 0x270A..=0x270B | 0x2728 | 0x274C | 0x274E | 0x2753..=0x2755 |
+        0x2757 | 0x2795..=0x2797 | 0x27B0 | 0x27BF | 0x2B1B..=0x2B1C | 0x2B50 | 0x2B55 => 2,
+        //emoji
+        0x1F004 | 0x1F0CF | 0x1F18E | 0x1F191..=0x1F19A | 0x1F200..=0x1F2FF |
+        0x1F300..=0x1F64F | 0x1F680..=0x1F6C5 | 0x1F6CC | 0x1F6D0..=0x1F6D2 | 0x1F6D5..=0x1F6D7 |
+        0x1F6DC..=0x1F6DF | 0x1F6EB..=0x1F6EC | 0x1F6F4..=0x1F6FC | 0x1F7E0..=0x1F7EB | 0x1F7F0 |
+        0x1F900..=0x1F9FF | 0x1FA70..=0x1FAFF => 2,
+        _ => 1,
+    }
+}
+
+pub fn str_width(text: &str) -> usize {
+    return text.chars().map(char_width).sum();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(str_width("abc"), 3);
+        assert_eq!(str_width("日本"), 4);
+        assert_eq!(str_width("🎉"), 2);
+        assert_eq!(str_width("🚀"), 2);
+        assert_eq!(str_width("🫠"), 2);
+        assert_eq!(str_width("⚡"), 2);
+        assert_eq!(str_width("☐"), 1);
+        assert_eq!(str_width("e\u{301}"), 1);
+    }
+}
@@ -0,0 +1,47 @@
+#![allow(clippy::needless_return)]
+
+//runs every file in tests/lex-corpus through the lexer in recovery mode and compares the
+//tokens, with where they start, and errors with the .expected file next to it. Run with
+//ANCODE_BLESS=1 to rewrite the expectations after an intended change, then review the diff.
+use std::path::Path;
+
+use compiler::json;
+use compiler::lexer::Lexer;
+use compiler::paths;
+
+fn render(source: String) -> String {
+    let (tokens, errors) = Lexer::new("corpus".into()).with_recovery().lex_with_errors(source);
+    let mut rendered = String::new();
+    for token in tokens {
+        let span = token.span();
+        rendered += &format!("{} {}:{} {}\n", token.kind().name(), span.start_line, span.start_index, json::string(token.text()));
+    }
+    for error in errors {
+        let span = error.span();
+        rendered += &format!("error {} {}:{}-{}:{}\n", error.error_type().name(),
+            span.start_line, span.start_index, span.end_line, span.end_index);
+    }
+    return rendered;
+}
+
+#[test]
+fn lex_corpus() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("lex-corpus");
+    let bless = std::env::var("ANCODE_BLESS").is_ok();
+    let mut failures = Vec::new();
+    for file in paths::find_source_files(&corpus).expect("Could not read the lex corpus") {
+        let source = std::fs::read_to_string(&file).expect("Could not read corpus file");
+        let rendered = render(source);
+        let expected_file = file.with_extension("expected");
+        if bless {
+            std::fs::write(&expected_file, &rendered).expect("Could not write expectation");
+            continue;
+        }
+        match std::fs::read_to_string(&expected_file) {
+            Ok(expected) if expected == rendered => {},
+            Ok(expected) => failures.push(format!("{}:\n--- expected\n{}--- actual\n{}", file.display(), expected, rendered)),
+            Err(_) => failures.push(format!("{}: missing {}", file.display(), expected_file.display())),
+        }
+    }
+    assert!(failures.is_empty(), "lex corpus mismatches:\n{}", failures.join("\n"));
+}